@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use maigacha::maigacha::PullMode;
+use maigacha::{Pull, PullList, PullType};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// A list of `count` common items and a tenth as many rares, with varied chances.
+fn large_list(count: usize) -> PullList {
+    let mut pull_list = PullList::new();
+    for i in 0..count {
+        let chance = (i % 7 + 1) as f64;
+        pull_list.insert(Pull::new(format!("Common {i}"), PullType::common(), chance));
+    }
+    for i in 0..count / 10 {
+        let chance = (i % 3 + 1) as f64;
+        pull_list.insert(Pull::new(format!("Rare {i}"), PullType::rare(), chance));
+    }
+    pull_list
+}
+
+fn pulls(c: &mut Criterion) {
+    let pull_list = large_list(5_000);
+    c.bench_function("pull_many 10k of 5.5k items", |b| {
+        b.iter(|| {
+            let mut pull_list = pull_list.clone();
+            let mut rng = StdRng::seed_from_u64(7);
+            pull_list.pull_many(10_000, false, &mut rng)
+        })
+    });
+    c.bench_function("simulate 100k of 5.5k items", |b| {
+        let mut rng = StdRng::seed_from_u64(7);
+        b.iter(|| pull_list.simulate(100_000, &mut rng))
+    });
+    let mut no_replacement = pull_list.clone();
+    no_replacement.set_pull_mode(PullMode::NoReplacement);
+    c.bench_function("simulate 20k of 5.5k items without replacement", |b| {
+        let mut rng = StdRng::seed_from_u64(7);
+        b.iter(|| no_replacement.simulate(20_000, &mut rng))
+    });
+}
+
+criterion_group!(benches, pulls);
+criterion_main!(benches);
@@ -0,0 +1,234 @@
+use crate::maigacha::PullList;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::{copy, read_to_string, rename, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const DEFAULT_BANNER: &str = "default";
+
+/// Version of the file layout written by this build.
+///
+/// 1. A single list, before banners.
+/// 2. Named banners.
+/// 3. History entries as objects with the banner they came from.
+pub const VERSION: u32 = 3;
+
+/// How banners are stored in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Toml,
+}
+impl FileFormat {
+    /// TOML for a `.toml` file, JSON for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+}
+impl FromStr for FileFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "toml" => Ok(Self::Toml),
+            _ => Err("Invalid file format, expected json or toml"),
+        }
+    }
+}
+
+/// Independent pull lists stored in one file, keyed by banner name.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Banners {
+    #[serde(default)]
+    pub version: u32,
+    pub banners: BTreeMap<String, PullList>,
+    /// Whether loading changed the file, so it's worth saving even after a
+    /// command that changes nothing.
+    #[serde(skip)]
+    pub migrated: bool,
+}
+
+impl Default for Banners {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Banners {
+    pub fn new() -> Self {
+        let mut banners = BTreeMap::new();
+        banners.insert(DEFAULT_BANNER.to_owned(), PullList::new());
+        Self {
+            version: VERSION,
+            banners,
+            migrated: false,
+        }
+    }
+
+    /// Upgrades a file of any older version one step at a time.
+    pub fn migrate(mut value: Value) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut version = match value.get("version").and_then(Value::as_u64) {
+            Some(version) => u32::try_from(version)?,
+            None if value.get("banners").is_some() => 2,
+            None => 1,
+        };
+        if version > VERSION {
+            return Err(format!("File version {version} is newer than supported {VERSION}").into());
+        }
+        while version < VERSION {
+            value = match version {
+                1 => {
+                    eprintln!("Migrating file from version 1 to 2: moving the list into the {DEFAULT_BANNER} banner.");
+                    json!({ "banners": { DEFAULT_BANNER: value } })
+                }
+                // Array entries are still read by `PullHistory` and written back as objects.
+                2 => {
+                    eprintln!("Migrating file from version 2 to 3: recording the banner of history entries.");
+                    value
+                }
+                _ => unreachable!(),
+            };
+            version += 1;
+        }
+        let mut banners: Self = serde_json::from_value(value)?;
+        banners.migrated = banners.version < VERSION;
+        banners.version = VERSION;
+        for (name, pull_list) in banners.banners.iter_mut() {
+            pull_list.set_banner(name);
+            pull_list.number_pulls();
+            for message in pull_list.fix_rarities() {
+                eprintln!("Banner {name}: {message}.");
+                banners.migrated = true;
+            }
+        }
+        Ok(banners)
+    }
+
+    /// Gets a banner, creating the default banner if it's missing.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PullList> {
+        if name == DEFAULT_BANNER {
+            return Some(self.banners.entry(DEFAULT_BANNER.to_owned()).or_default());
+        }
+        self.banners.get_mut(name)
+    }
+
+    /// Adds an empty banner, returning `false` if it already exists.
+    pub fn create(&mut self, name: &str) -> bool {
+        if self.banners.contains_key(name) {
+            return false;
+        }
+        let mut pull_list = PullList::new();
+        pull_list.set_banner(name);
+        self.banners.insert(name.to_owned(), pull_list);
+        true
+    }
+
+    /// Saves by writing a temporary file next to `file_path` and renaming it over,
+    /// so a crash mid-write never leaves a truncated file behind.
+    ///
+    /// With `backup`, an existing file is first copied to `<file_path>.bak`.
+    /// A failed backup is reported but doesn't stop the save.
+    pub fn save_to_file(
+        &self,
+        file_path: &Path,
+        format: FileFormat,
+        backup: bool,
+        pretty: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let text = self.to_text(format, pretty)?;
+
+        if backup && file_path.exists() {
+            if let Err(err) = copy(file_path, with_suffix(file_path, ".bak")) {
+                eprintln!("Could not back up {}: {err}", file_path.display());
+            }
+        }
+
+        let tmp_path = with_suffix(file_path, ".tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        rename(&tmp_path, file_path)?;
+
+        Ok(())
+    }
+
+    pub fn to_text(
+        &self,
+        format: FileFormat,
+        pretty: bool,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(match (format, pretty) {
+            (FileFormat::Json, true) => serde_json::to_string_pretty(self)?,
+            (FileFormat::Json, false) => serde_json::to_string(self)?,
+            (FileFormat::Toml, true) => toml::to_string_pretty(self)?,
+            (FileFormat::Toml, false) => toml::to_string(self)?,
+        })
+    }
+
+    /// Loads banners, migrating files written by older versions.
+    pub fn load_from_file(
+        file_path: &Path,
+        format: FileFormat,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let file_contents = read_to_string(file_path)?;
+
+        Self::load_from_str(&file_contents, format)
+    }
+
+    /// Parses banners, migrating files written by older versions.
+    ///
+    /// TOML is read into the same `Value` as JSON, so both share the migrations.
+    pub fn load_from_str(
+        text: &str,
+        format: FileFormat,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let value: Value = match format {
+            FileFormat::Json => serde_json::from_str(text)?,
+            FileFormat::Toml => serde_json::to_value(toml::from_str::<toml::Value>(text)?)?,
+        };
+
+        Self::migrate(value)
+    }
+}
+
+/// `path` with `suffix` appended to its file name, keeping non-UTF-8 names intact.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    name.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maigacha::{Pull, PullType};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn loads_and_pulls_with_zero_rare_rarity() {
+        let mut pull_list = PullList::new();
+        pull_list.insert(Pull::new("Common".to_owned(), PullType::common(), 1_f64));
+        pull_list.insert(Pull::new("Rare".to_owned(), PullType::rare(), 1_f64));
+        pull_list.rare_rarity = 0;
+        let current = json!({ "version": VERSION, "banners": { DEFAULT_BANNER: &pull_list } });
+        let legacy = serde_json::to_value(&pull_list).unwrap();
+
+        for value in [current, legacy] {
+            let mut banners = Banners::load_from_str(&value.to_string(), FileFormat::Json).unwrap();
+            let pull_list = banners.get_mut(DEFAULT_BANNER).unwrap();
+            assert_eq!(pull_list.rare_rarity, 1);
+            let mut rng = StdRng::seed_from_u64(0);
+            for _ in 0..100 {
+                assert!(pull_list.pull(&mut rng).is_some());
+            }
+        }
+    }
+}
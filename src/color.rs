@@ -0,0 +1,32 @@
+const NAMES: [(&str, u8); 8] = [
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+];
+
+/// ANSI escape for a color name, optionally prefixed with `bright-`,
+/// or for a raw SGR code such as `35` or `1;35`.
+pub fn escape(color: &str) -> Result<String, String> {
+    let color = color.trim().to_lowercase();
+    let (bright, name) = match color.strip_prefix("bright-") {
+        Some(name) => (true, name),
+        None => (false, color.as_str()),
+    };
+    if let Some((_, code)) = NAMES.iter().find(|(n, _)| *n == name) {
+        let code = if bright { code + 60 } else { *code };
+        return Ok(format!("\x1b[{code}m"));
+    }
+    let is_code = !color.is_empty() && color.split(';').all(|part| part.parse::<u8>().is_ok());
+    if is_code {
+        return Ok(format!("\x1b[{color}m"));
+    }
+    Err(format!(
+        r#"Unknown color "{color}", expected {} (optionally bright-) or an ANSI code like 35"#,
+        NAMES.map(|(name, _)| name).join(", ")
+    ))
+}
@@ -0,0 +1,21 @@
+//! Random pulls from a list of items with rarity tiers, pity and a history.
+//!
+//! ```
+//! use maigacha::{Pull, PullList, PullType};
+//! use rand::rngs::StdRng;
+//! use rand::SeedableRng;
+//!
+//! let mut pull_list = PullList::new();
+//! pull_list.insert(Pull::new("Sword".to_owned(), PullType::common(), 1.0));
+//! pull_list.insert(Pull::new("Crown".to_owned(), PullType::rare(), 1.0));
+//!
+//! let mut rng = StdRng::seed_from_u64(7);
+//! let pull = pull_list.pull(&mut rng).expect("the list has items");
+//! println!("Pulled {}", pull.name);
+//! ```
+pub mod banner;
+pub mod color;
+mod csv;
+pub mod maigacha;
+
+pub use crate::maigacha::{Pull, PullHistory, PullList, PullType};
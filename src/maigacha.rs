@@ -1,33 +1,278 @@
-use chrono::{DateTime, Local};
+use crate::banner::DEFAULT_BANNER;
+use crate::{color, csv};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, Utc};
 use rand::Rng;
-use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
-use std::fs::{read_to_string, File};
-use std::io::Write;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{Read, Write};
 use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PullType {
-    Common,
-    Rare,
+/// Rare chance below which `validate` warns that rares are close to impossible.
+pub const LOW_RARE_CHANCE: f64 = 0.001;
+
+/// Most items `add_many` inserts at once.
+pub const MAX_ADD_MANY: usize = 10_000;
+
+/// Most decimal places `PullList::round` keeps.
+pub const MAX_ROUND_PLACES: u32 = 10;
+
+/// Largest balance or cost, the biggest integer a TOML file can hold.
+pub const MAX_BALANCE: u64 = i64::MAX as u64;
+
+/// Longest cooldown `set-cooldown` accepts, a year.
+pub const MAX_COOLDOWN_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// Decimal places shown for chances unless `list --precision` says otherwise.
+pub const DEFAULT_PRECISION: usize = 4;
+
+/// Name of a rarity tier, stored lowercase.
+///
+/// `common` is the base tier, every other tier counts as rare.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub struct PullType(String);
+
+impl PullType {
+    pub fn common() -> Self {
+        Self("common".to_owned())
+    }
+    pub fn rare() -> Self {
+        Self("rare".to_owned())
+    }
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+    pub fn is_common(&self) -> bool {
+        self.0 == "common"
+    }
+}
+impl From<String> for PullType {
+    fn from(s: String) -> Self {
+        Self(s.to_lowercase())
+    }
+}
+impl From<PullType> for String {
+    fn from(pull_type: PullType) -> Self {
+        pull_type.0
+    }
+}
+impl fmt::Display for PullType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut chars = self.0.chars();
+        if let Some(first) = chars.next() {
+            write!(f, "{}{}", first.to_uppercase(), chars.as_str())?;
+        }
+        Ok(())
+    }
 }
 impl FromStr for PullType {
     type Err = &'static str;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s.contains([':', ',']) {
+            return Err("Invalid pull type");
+        }
+        Ok(Self(s.to_lowercase()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Name,
+    Chance,
+}
+impl FromStr for SortBy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(Self::Name),
+            "chance" => Ok(Self::Chance),
+            _ => Err("Invalid sort, expected name or chance"),
+        }
+    }
+}
+
+/// Whether pulled items go back into the pool.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PullMode {
+    #[default]
+    Replacement,
+    /// A pulled item can't be pulled again until every item has been,
+    /// then the pool is refilled.
+    NoReplacement,
+}
+impl FromStr for PullMode {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "replacement" => Ok(Self::Replacement),
+            "no-replacement" => Ok(Self::NoReplacement),
+            _ => Err("Invalid pull mode, expected replacement or no-replacement"),
+        }
+    }
+}
+
+/// What `PullList::merge` does with an item whose name is already in the list.
+#[derive(Debug, Clone, Copy)]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    /// Adds the item with a ` (2)`, ` (3)`, ... suffix.
+    Rename,
+}
+impl FromStr for ConflictPolicy {
+    type Err = &'static str;
+
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "common" => Ok(Self::Common),
-            "rare" => Ok(Self::Rare),
-            _ => Err("Invalid pull type"),
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "rename" => Ok(Self::Rename),
+            _ => Err("Invalid conflict policy, expected skip, overwrite or rename"),
+        }
+    }
+}
+
+/// What `PullList::merge` did with the merged items.
+#[derive(Debug, Default)]
+pub struct MergeSummary {
+    pub added: usize,
+    pub skipped: usize,
+    pub overwritten: usize,
+    pub renamed: usize,
+    pub tiers_added: usize,
+}
+
+impl SortBy {
+    /// Sorts the borrowed items, breaking ties on chance by name.
+    pub fn sort(self, pulls: &mut [&Pull], desc: bool) {
+        pulls.sort_by(|a, b| {
+            let ordering = match self {
+                Self::Name => a.name.cmp(&b.name),
+                Self::Chance => a.chance.total_cmp(&b.chance).then(a.name.cmp(&b.name)),
+            };
+            if desc {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+}
+
+/// How `PullList::print_list` lays out the items.
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    pub sort: Option<SortBy>,
+    pub desc: bool,
+    /// 1-based page to show, or every item when `None`.
+    pub page: Option<usize>,
+    pub per_page: usize,
+    /// List every item in one run instead of grouping by tier.
+    pub flat: bool,
+    /// Only list items with this tag.
+    pub tag: Option<String>,
+    /// Only list items in this tier.
+    pub pull_type: Option<PullType>,
+    /// Decimal places of the chances.
+    pub precision: usize,
+    /// Show each item's note under it.
+    pub notes: bool,
+}
+
+/// A configured tier, rolled as 1 in `rarity` once a pull leaves the common tier.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tier {
+    pub name: PullType,
+    pub rarity: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl Tier {
+    pub fn new(name: PullType, rarity: usize) -> Self {
+        Self {
+            name,
+            rarity,
+            color: None,
+        }
+    }
+}
+
+fn default_tiers() -> Vec<Tier> {
+    vec![
+        Tier::new(PullType::common(), 1),
+        Tier::new(PullType::rare(), 1),
+    ]
+}
+
+/// A chance as typed, a plain weight or a percentage like `25%`.
+///
+/// Both are pulled by their number, the percent sign only changes how it's shown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Chance {
+    Weight(f64),
+    Percent(f64),
+}
+impl Chance {
+    pub fn value(self) -> f64 {
+        match self {
+            Self::Weight(value) | Self::Percent(value) => value,
         }
     }
 }
+impl FromStr for Chance {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, percent) = match s.strip_suffix('%') {
+            Some(number) => (number.trim_end(), true),
+            None => (s, false),
+        };
+        let value = number.parse::<f64>().map_err(|_| "Invalid chance")?;
+        let value = check_chance(value)?;
+        Ok(if percent {
+            Self::Percent(value)
+        } else {
+            Self::Weight(value)
+        })
+    }
+}
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Pull {
     pub name: String,
     pub pull_type: PullType,
     pub chance: f64,
+    /// Whether `chance` was entered as a percentage.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub percent: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+    /// Disabled items stay in the list with their chance but can't be pulled.
+    #[serde(default = "default_enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+    /// Lowercase categories, such as "weapons" or "seasonal".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// A reminder shown by `list --notes`, ignored when pulling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
 }
 
 impl Pull {
@@ -36,8 +281,83 @@ impl Pull {
             name,
             pull_type,
             chance,
+            percent: false,
+            favorite: false,
+            enabled: true,
+            tags: Vec::new(),
+            note: None,
+        }
+    }
+
+    pub fn with_chance(name: String, pull_type: PullType, chance: Chance) -> Self {
+        let mut pull = Self::new(name, pull_type, 0_f64);
+        pull.set_chance(chance);
+        pull
+    }
+
+    pub fn set_chance(&mut self, chance: Chance) {
+        self.chance = chance.value();
+        self.percent = matches!(chance, Chance::Percent(_));
+    }
+
+    /// The quoted name, starred for favorites and marked when disabled.
+    pub fn label(&self) -> String {
+        let mut label = format!("\"{}\"", self.name);
+        if self.favorite {
+            label.push_str(" ★");
+        }
+        if !self.enabled {
+            label.push_str(" (disabled)");
+        }
+        label
+    }
+
+    /// The chance as it was entered, with a `%` for percentages.
+    pub fn chance_text(&self) -> String {
+        if self.percent {
+            format!("{}%", self.chance)
+        } else {
+            self.chance.to_string()
+        }
+    }
+
+    /// The chance rounded to `precision` decimals for display, with a `%` for percentages.
+    pub fn rounded_chance_text(&self, precision: usize) -> String {
+        let chance = format_chance(self.chance, precision);
+        if self.percent {
+            format!("{chance}%")
+        } else {
+            chance
+        }
+    }
+
+    /// Sets the note, removing it when `note` is blank.
+    pub fn set_note(&mut self, note: String) {
+        self.note = (!note.trim().is_empty()).then_some(note);
+    }
+
+    pub fn add_tag(&mut self, tag: &str) {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !self.has_tag(&tag) {
+            self.tags.push(tag);
         }
     }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim().to_lowercase();
+        self.tags.contains(&tag)
+    }
+}
+
+/// Checks that `chance` is a usable weight, finite and above 0.
+pub fn check_chance(chance: f64) -> Result<f64, &'static str> {
+    if !chance.is_finite() {
+        Err("chance must be a finite number")
+    } else if chance <= 0_f64 {
+        Err("chance can't be 0 or less")
+    } else {
+        Ok(chance)
+    }
 }
 
 impl FromStr for Pull {
@@ -48,65 +368,574 @@ impl FromStr for Pull {
 
         if parts.len() == 3 {
             let name = parts[0].to_owned();
-            let chance = parts[2].parse::<f64>().map_err(|_| "Invalid chance")?;
+            let chance = Chance::from_str(parts[2])?;
             let pull_type = PullType::from_str(parts[1])?;
 
-            return Ok(Self {
-                name,
-                pull_type,
-                chance,
-            });
+            return Ok(Self::with_chance(name, pull_type, chance));
         }
 
         Err("Invalid pull chance string")
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PullList {
-    pub list: Vec<Pull>,
+    /// Private so the tables pulls reuse can't go stale, read with `list()`.
+    list: Vec<Pull>,
     pub pull_history: PullHistory,
     pub rare_rarity: usize,
+    #[serde(default)]
+    pub pity_counter: usize,
+    #[serde(default = "default_pity_threshold")]
+    pub pity_threshold: usize,
+    #[serde(default = "default_tiers")]
+    pub tiers: Vec<Tier>,
+    /// Pulls made over the list's lifetime, unlike the capped history.
+    #[serde(default)]
+    pub total_pulls: u64,
+    /// Common pulls in a row since the last rare, for display only.
+    #[serde(default)]
+    pub dry_streak: usize,
+    /// Weight of landing in each tier, normalized over the tiers with items.
+    /// When empty, weights are derived from `rare_rarity` and the tier rarities.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tier_weights: HashMap<PullType, f64>,
+    /// Item whose chance is multiplied by `rate_up_factor` within its tier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_up: Option<String>,
+    #[serde(default = "default_rate_up_factor")]
+    pub rate_up_factor: f64,
+    /// Minimum seconds between pulls, measured from the last history entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_secs: Option<u64>,
+    /// Balance deducted by each pull, 0 for free pulls.
+    #[serde(default)]
+    pub cost: u64,
+    #[serde(default)]
+    pub balance: u64,
+    /// Dry streak after which the rare chance ramps up until `pity_threshold`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft_pity_start: Option<usize>,
+    #[serde(default)]
+    pub pull_mode: PullMode,
+    /// Force a rare whenever none is left in the history, so the first pull of a
+    /// new list is rare and a rare turns up at least once every history `size` pulls.
+    #[serde(default = "default_guarantee_first_rare")]
+    pub guarantee_first_rare: bool,
+    /// `pull_id` of the latest pull, never reused even after an undo.
+    #[serde(default)]
+    pub last_pull_id: u64,
+    /// Printed when a rare is pulled, with `{name}` and `{chance}` filled in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rare_message: Option<String>,
+    /// Items pulled since the pool was last refilled, in `PullMode::NoReplacement`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drawn: Vec<String>,
+    /// Times the pool was refilled since the list was loaded.
+    #[serde(skip)]
+    pub pool_resets: usize,
+    /// Banner the list is stored under, recorded as the source of its pulls.
+    #[serde(skip)]
+    pub banner: String,
+    #[serde(skip)]
+    tables: TableCache,
+}
+
+fn default_pity_threshold() -> usize {
+    90
+}
+
+fn default_guarantee_first_rare() -> bool {
+    true
+}
+
+fn default_rate_up_factor() -> f64 {
+    2_f64
+}
+
+/// A recorded pull.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    /// Number of the pull, counting up from 1 across the list's whole history.
+    #[serde(default)]
+    pub pull_id: u64,
+    pub date_time: DateTime<FixedOffset>,
+    pub pull_type: PullType,
+    pub name: String,
+    /// Banner the pull came from.
+    pub source: String,
+    /// Balance the pull cost, given back if it's undone.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cost: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Reads history entries, also accepting the `[date_time, pull_type, name]`
+/// and `[date_time, pull_type, name, source]` arrays written by older versions.
+///
+/// Entries saved without a source get an empty one, filled in by `PullList::set_banner`.
+fn deserialize_history<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<VecDeque<HistoryEntry>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SavedEntry {
+        Entry(HistoryEntry),
+        Sourced(DateTime<FixedOffset>, PullType, String, String),
+        Unsourced(DateTime<FixedOffset>, PullType, String),
+    }
+    let entries = VecDeque::<SavedEntry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            SavedEntry::Entry(entry) => entry,
+            SavedEntry::Sourced(date_time, pull_type, name, source) => HistoryEntry {
+                pull_id: 0,
+                date_time,
+                pull_type,
+                name,
+                source,
+                cost: 0,
+            },
+            SavedEntry::Unsourced(date_time, pull_type, name) => HistoryEntry {
+                pull_id: 0,
+                date_time,
+                pull_type,
+                name,
+                source: String::new(),
+                cost: 0,
+            },
+        })
+        .collect())
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PullHistory {
-    pub history: VecDeque<(DateTime<Local>, PullType, String)>,
+    #[serde(deserialize_with = "deserialize_history")]
+    pub history: VecDeque<HistoryEntry>,
     pub size: usize,
+    /// Record and show timestamps in UTC instead of local time.
+    #[serde(default)]
+    pub utc: bool,
+    /// strftime-style format for shown timestamps, see `format_entry` for the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_format: Option<String>,
+}
+#[derive(Serialize, Debug)]
+pub struct HistoryStats {
+    pub total: usize,
+    pub counts: HashMap<PullType, usize>,
+    pub most_pulled: Option<(String, usize)>,
+    /// Pulls in a row at the end of the history that were all common or all rare.
+    pub current_streak: usize,
+    /// Whether `current_streak` is of rares.
+    pub current_streak_rare: bool,
+    pub longest_common_streak: usize,
+    pub longest_rare_streak: usize,
 }
+
 impl PullHistory {
     pub fn new(size: usize) -> Self {
         Self {
             history: VecDeque::with_capacity(size),
             size,
+            utc: false,
+            timestamp_format: None,
+        }
+    }
+    pub fn update(
+        &mut self,
+        pull_id: u64,
+        pull_type: PullType,
+        name: String,
+        source: String,
+        cost: u64,
+    ) {
+        let date_time = if self.utc {
+            Utc::now().into()
+        } else {
+            Local::now().into()
+        };
+        self.history.push_back(HistoryEntry {
+            pull_id,
+            date_time,
+            pull_type,
+            name,
+            source,
+            cost,
+        });
+        if self.history.len() > self.size {
+            self.history.pop_front();
         }
     }
-    pub fn update(&mut self, pull_type: PullType, name: String) {
-        let date_time = Local::now();
-        self.history.push_back((date_time, pull_type, name));
-        if self.history.len() >= self.size {
+    pub fn undo(&mut self) -> Option<HistoryEntry> {
+        self.history.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+    /// Changes the size, dropping the oldest entries that no longer fit.
+    pub fn resize(&mut self, size: usize) {
+        self.size = size;
+        while self.history.len() > self.size {
             self.history.pop_front();
         }
     }
-    pub fn contains(&self, pull_type: PullType) -> bool {
-        self.history.iter().any(|(_, pt, _)| *pt == pull_type)
+    /// How many times each item name appears in the history.
+    pub fn counts(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for entry in &self.history {
+            *counts.entry(entry.name.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+    pub fn stats(&self) -> HistoryStats {
+        let mut counts = HashMap::new();
+        let mut streak = 0;
+        let mut streak_rare = false;
+        let mut longest_common_streak = 0;
+        let mut longest_rare_streak = 0;
+        for HistoryEntry { pull_type, .. } in &self.history {
+            *counts.entry(pull_type.clone()).or_insert(0) += 1;
+            let rare = !pull_type.is_common();
+            if streak > 0 && rare == streak_rare {
+                streak += 1;
+            } else {
+                streak = 1;
+                streak_rare = rare;
+            }
+            if rare {
+                longest_rare_streak = longest_rare_streak.max(streak);
+            } else {
+                longest_common_streak = longest_common_streak.max(streak);
+            }
+        }
+        let most_pulled = self
+            .counts()
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+        HistoryStats {
+            total: self.history.len(),
+            counts,
+            most_pulled,
+            current_streak: streak,
+            current_streak_rare: streak_rare,
+            longest_common_streak,
+            longest_rare_streak,
+        }
+    }
+    /// When the newest entry was pulled, in local time.
+    pub fn last_pull_time(&self) -> Option<DateTime<Local>> {
+        self.history
+            .back()
+            .map(|entry| entry.date_time.with_timezone(&Local))
+    }
+    pub fn contains_rare(&self) -> bool {
+        self.history
+            .iter()
+            .any(|entry| !entry.pull_type.is_common())
+    }
+    /// Entries between `start` and `end`, both inclusive.
+    ///
+    /// A missing `end` means up to now.
+    pub fn filter_by_date(
+        &self,
+        start: Option<DateTime<Local>>,
+        end: Option<DateTime<Local>>,
+    ) -> Vec<&HistoryEntry> {
+        let end = end.unwrap_or_else(Local::now);
+        self.history
+            .iter()
+            .filter(|entry| start.is_none_or(|start| entry.date_time >= start))
+            .filter(|entry| entry.date_time <= end)
+            .collect()
+    }
+    /// Writes the history as CSV with a `timestamp,pull_type,name,source` header.
+    pub fn export_csv(&self, mut writer: impl Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(writer, "timestamp,pull_type,name,source")?;
+        for entry in &self.history {
+            let timestamp = entry.date_time.to_rfc3339();
+            writeln!(
+                writer,
+                "{}",
+                csv::record(&[
+                    &timestamp,
+                    entry.pull_type.name(),
+                    &entry.name,
+                    &entry.source
+                ])
+            )?;
+        }
+        Ok(())
+    }
+    /// Sets the timestamp format, or goes back to the default with `None`.
+    pub fn set_timestamp_format(&mut self, format: Option<String>) -> Result<(), &'static str> {
+        if format
+            .as_deref()
+            .is_some_and(|format| StrftimeItems::new(format).any(|item| item == Item::Error))
+        {
+            return Err("invalid timestamp format");
+        }
+        self.timestamp_format = format;
+        Ok(())
     }
     pub fn print(&self) {
+        self.print_filtered(None, None, None, None);
+    }
+    /// Entries whose name contains `query`, ignoring case.
+    pub fn filter_by_name(&self, query: &str) -> Vec<&HistoryEntry> {
+        self.filter(None, None, None, Some(query))
+    }
+    /// Entries of `pull_type` between `start` and `end` whose name contains
+    /// `name` ignoring case, all of them when `None`.
+    pub fn filter(
+        &self,
+        pull_type: Option<&PullType>,
+        start: Option<DateTime<Local>>,
+        end: Option<DateTime<Local>>,
+        name: Option<&str>,
+    ) -> Vec<&HistoryEntry> {
+        let entries = if start.is_some() || end.is_some() {
+            self.filter_by_date(start, end)
+        } else {
+            self.history.iter().collect()
+        };
+        let name = name.map(str::to_lowercase);
+        entries
+            .into_iter()
+            .filter(|entry| pull_type.is_none_or(|pull_type| entry.pull_type == *pull_type))
+            .filter(|entry| {
+                name.as_ref()
+                    .is_none_or(|name| entry.name.to_lowercase().contains(name))
+            })
+            .collect()
+    }
+    pub fn print_filtered(
+        &self,
+        pull_type: Option<&PullType>,
+        start: Option<DateTime<Local>>,
+        end: Option<DateTime<Local>>,
+        name: Option<&str>,
+    ) {
         if self.history.is_empty() {
             println!("History is empty.");
+            return;
+        }
+        let entries = self
+            .filter(pull_type, start, end, name)
+            .into_iter()
+            .map(|entry| self.format_entry(entry))
+            .collect::<Vec<_>>();
+        if let (Some(name), true) = (name, entries.is_empty()) {
+            println!(r#"No pulls matching "{name}" in history."#);
+            return;
+        }
+        match pull_type {
+            Some(pull_type) if entries.is_empty() => {
+                println!("No {pull_type} pulls in history.");
+            }
+            None if entries.is_empty() => println!("No pulls in history for that range."),
+            _ => println!("{}", entries.join(",\n")),
+        }
+    }
+    /// Formats an entry in UTC with a `Z` suffix, or in local time.
+    ///
+    /// A set `timestamp_format` replaces both default formats.
+    pub fn format_entry(&self, entry: &HistoryEntry) -> String {
+        let date_time = if self.utc {
+            let format = self
+                .timestamp_format
+                .as_deref()
+                .unwrap_or("%Y-%m-%d %H:%M:%SZ");
+            entry.date_time.with_timezone(&Utc).format(format)
+        } else {
+            let format = self
+                .timestamp_format
+                .as_deref()
+                .unwrap_or("%Y-%m-%d %H:%M:%S");
+            entry.date_time.with_timezone(&Local).format(format)
+        };
+        format!(
+            "#{} {date_time} {} \"{}\" from {}",
+            entry.pull_id, entry.pull_type, entry.name, entry.source
+        )
+    }
+}
+
+/// Picks one of `items` with probability proportional to its weight.
+///
+/// Walks `items` twice, once for the sum and once to pick, so it doesn't allocate.
+/// Returns `None` when the weights don't sum to a positive finite number.
+pub fn select_weighted<T, R: Rng + ?Sized>(
+    items: impl Iterator<Item = (T, f64)> + Clone,
+    rng: &mut R,
+) -> Option<T> {
+    let weights_sum: f64 = items.clone().map(|(_, weight)| weight).sum();
+    if !(weights_sum > 0_f64 && weights_sum.is_finite()) {
+        return None;
+    }
+    let select = rng.gen_range(0.0_f64..weights_sum);
+    let mut curr_weight = 0.0_f64;
+    let mut last = None;
+    for (item, weight) in items {
+        curr_weight += weight;
+        if curr_weight > select {
+            return Some(item);
+        }
+        last = Some(item);
+    }
+    // Rounding can leave the running sum just short of `select`.
+    last
+}
+
+/// The items of one tier with the running sum of their effective chances.
+#[derive(Debug, Clone)]
+struct TierItems {
+    pull_type: PullType,
+    /// Indices into `list`, sorted by name.
+    indices: Vec<usize>,
+    /// Sum of the effective chances up to and including each of `indices`.
+    cumulative: Vec<f64>,
+    /// Unnormalized weight of landing in the tier before pity, see
+    /// `PullList::tier_weights_for`. 0 while it has nothing to pull.
+    weight: f64,
+}
+
+impl TierItems {
+    fn sum(&self) -> f64 {
+        self.cumulative.last().copied().unwrap_or(0_f64)
+    }
+
+    /// Picks one of `indices` by its share of the chances with a binary search.
+    ///
+    /// Draws the same number from `rng` and gives the same item as
+    /// `select_weighted` over the tier would.
+    fn select<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<usize> {
+        let sum = self.sum();
+        if !(sum > 0_f64 && sum.is_finite()) {
+            return None;
+        }
+        let select = rng.gen_range(0.0_f64..sum);
+        let position = self
+            .cumulative
+            .partition_point(|&curr_chance| curr_chance <= select);
+        // Rounding can leave the running sum just short of `select`.
+        self.indices.get(position).or(self.indices.last()).copied()
+    }
+
+    /// Takes `index` out, redoing the running sums after it in the same order
+    /// a fresh table would add them up.
+    fn remove(&mut self, index: usize, weight: impl Fn(usize) -> f64) {
+        let Some(position) = self.indices.iter().position(|&i| i == index) else {
+            return;
+        };
+        self.indices.remove(position);
+        self.cumulative.truncate(position);
+        let mut sum = self.sum();
+        for &i in &self.indices[position..] {
+            sum += weight(i);
+            self.cumulative.push(sum);
         }
-        println!(
-            "{}",
-            self.history
-                .iter()
-                .map(|(date_time, pull_type, name)| format!(
-                    "{} {:#?} \"{}\"",
-                    date_time.format("%Y-%m-%d %H:%M:%S"),
-                    pull_type,
-                    name
-                ))
-                .collect::<Vec<_>>()
-                .join(",\n")
-        );
+    }
+}
+
+/// Tables kept between pulls so each pull doesn't rebuild them.
+///
+/// Dropped by `PullList::invalidate_tables` whenever what they're built from changes.
+#[derive(Debug, Clone, Default)]
+struct TableCache {
+    /// `PullList::tier_table`, built by the first pull.
+    all: Option<Vec<TierItems>>,
+    /// `PullList::pool_table`, with each draw taken out as it's made.
+    pool: Option<Vec<TierItems>>,
+}
+
+/// A duration in its two largest units, like `3h 12m`, or seconds under a minute.
+fn format_elapsed(elapsed: chrono::Duration) -> String {
+    let secs = elapsed.num_seconds().max(0);
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {}s", secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+fn print_note(pull: &Pull) {
+    if let Some(note) = &pull.note {
+        println!("    {note}");
+    }
+}
+
+/// `chance` rounded to `precision` decimals, without trailing zeros so whole
+/// chances stay `1` rather than `1.0000`.
+fn format_chance(chance: f64, precision: usize) -> String {
+    let text = format!("{chance:.precision$}");
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+/// `part` as a fraction of `whole`, or 0 when `whole` is, like a tier whose
+/// items are all disabled.
+fn share(part: f64, whole: f64) -> f64 {
+    if whole > 0_f64 {
+        part / whole
+    } else {
+        0_f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found by `PullList::validate`.
+#[derive(Debug)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+impl Default for PullList {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -116,100 +945,1875 @@ impl PullList {
             list: Vec::new(),
             pull_history: PullHistory::new(35),
             rare_rarity: 100,
+            pity_counter: 0,
+            pity_threshold: default_pity_threshold(),
+            tiers: default_tiers(),
+            total_pulls: 0,
+            dry_streak: 0,
+            rate_up: None,
+            rate_up_factor: default_rate_up_factor(),
+            cooldown_secs: None,
+            cost: 0,
+            soft_pity_start: None,
+            balance: 0,
+            pull_mode: PullMode::Replacement,
+            guarantee_first_rare: default_guarantee_first_rare(),
+            last_pull_id: 0,
+            rare_message: None,
+            drawn: Vec::new(),
+            pool_resets: 0,
+            tier_weights: HashMap::new(),
+            banner: DEFAULT_BANNER.to_owned(),
+            tables: TableCache::default(),
         }
     }
 
-    pub fn insert(&mut self, pull: Pull) {
-        self.list.push(pull);
+    pub fn contains(&self, name: &str) -> bool {
+        self.list.iter().any(|pull| pull.name == name)
     }
 
-    pub fn remove(&mut self, name: &str) -> Option<Pull> {
-        if let Some(index) = self.list.iter().position(|pull| pull.name == name) {
-            return Some(self.list.remove(index));
+    /// Position of the item named `name` in `list`, comparing case-insensitively
+    /// when `ignore_case` is set.
+    ///
+    /// Fails when nothing matches, or when several items match ignoring case.
+    pub fn find_index(&self, name: &str, ignore_case: bool) -> Result<usize, &'static str> {
+        if !ignore_case {
+            return self
+                .list
+                .iter()
+                .position(|pull| pull.name == name)
+                .ok_or("not in list");
+        }
+        let name = name.to_lowercase();
+        let mut matches = self
+            .list
+            .iter()
+            .enumerate()
+            .filter(|(_, pull)| pull.name.to_lowercase() == name)
+            .map(|(index, _)| index);
+        match (matches.next(), matches.next()) {
+            (Some(index), None) => Ok(index),
+            (Some(_), Some(_)) => Err("matches several items ignoring case, use the exact name"),
+            (None, _) => Err("not in list"),
         }
-        None
     }
 
-    pub fn pull(&mut self) -> Option<&Pull> {
-        if self.list.is_empty() {
-            return None;
+    /// Inserts `pull`, returning `false` if an item with that name already exists.
+    pub fn insert(&mut self, pull: Pull) -> bool {
+        if self.contains(&pull.name) {
+            return false;
         }
-        let mut rng = rand::thread_rng();
-        let (common, rare): (Vec<&Pull>, Vec<&Pull>) =
-            self.list.iter().partition(|pull| match pull.pull_type {
-                PullType::Common => true,
-                PullType::Rare => false,
-            });
-
-        let common_sum: f64 = common.iter().map(|pull| pull.chance).sum();
-        let rare_sum: f64 = rare.iter().map(|pull| pull.chance).sum();
-
-        let (pulls, pulls_sum, pulled_type) = if !rare.is_empty()
-            && (common.is_empty()
-                || rng.gen_range(0..self.rare_rarity) == 0
-                || !self.pull_history.contains(PullType::Rare))
-        {
-            (rare, rare_sum, PullType::Rare)
-        } else {
-            (common, common_sum, PullType::Common)
-        };
-
-        let select = rng.gen_range(0.0_f64..pulls_sum);
-        let mut curr_chance = 0.0_f64;
+        self.list.push(pull);
+        self.invalidate_tables();
+        true
+    }
 
-        for pull in (&pulls).iter() {
-            curr_chance += pull.chance;
-            if curr_chance > select {
-                self.pull_history.update(pulled_type, pull.name.clone());
-                return Some(*pull);
+    /// Inserts `pull`, overwriting an existing item with the same name in place.
+    pub fn replace(&mut self, pull: Pull) -> Option<Pull> {
+        self.invalidate_tables();
+        match self.list.iter_mut().find(|p| p.name == pull.name) {
+            Some(existing) => Some(std::mem::replace(existing, pull)),
+            None => {
+                self.list.push(pull);
+                None
             }
         }
-        unreachable!();
     }
 
-    pub fn save_to_json(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let json_string = serde_json::to_string(self)?;
-
-        let mut file = File::create(file_path)?;
-        file.write_all(json_string.as_bytes())?;
+    pub fn remove(&mut self, name: &str) -> Option<Pull> {
+        let index = self.find_index(name, false).ok()?;
+        self.remove_at(index)
+    }
 
-        Ok(())
+    pub fn remove_at(&mut self, index: usize) -> Option<Pull> {
+        if index < self.list.len() {
+            self.invalidate_tables();
+            return Some(self.list.remove(index));
+        }
+        None
     }
-    pub fn load_from_json_file(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let file_contents = read_to_string(file_path)?;
 
-        let pull_list = serde_json::from_str(&file_contents)?;
+    /// The items, changed only through methods like `insert` and `edit`.
+    pub fn list(&self) -> &[Pull] {
+        &self.list
+    }
 
-        Ok(pull_list)
+    /// Drops the tables pulls reuse. Call it after changing `tiers`, `rare_rarity`,
+    /// `tier_weights`, `rate_up`, `rate_up_factor` or `drawn` directly rather
+    /// than through a method.
+    pub fn invalidate_tables(&mut self) {
+        self.tables = TableCache::default();
     }
 
-    pub fn print_list(&self) {
-        if self.list.is_empty() {
-            println!("No items to list");
-            return;
+    pub fn edit(
+        &mut self,
+        name: &str,
+        chance: Option<Chance>,
+        pull_type: Option<PullType>,
+        note: Option<String>,
+    ) -> Result<(), &'static str> {
+        if pull_type
+            .as_ref()
+            .is_some_and(|pull_type| !self.has_tier(pull_type))
+        {
+            return Err("unknown tier");
         }
-        let (common, rare): (Vec<&Pull>, Vec<&Pull>) =
-            self.list.iter().partition(|pull| match pull.pull_type {
-                PullType::Common => true,
-                PullType::Rare => false,
-            });
-        if !common.is_empty() {
-            println!("-Common Pulls-");
-            Self::print_pull_vec(&common);
+        let index = self.find_index(name, false)?;
+        self.invalidate_tables();
+        let pull = &mut self.list[index];
+        if let Some(chance) = chance {
+            pull.set_chance(chance);
         }
-        if !rare.is_empty() {
-            println!("-Rare Pulls-");
-            Self::print_pull_vec(&rare);
+        if let Some(pull_type) = pull_type {
+            pull.pull_type = pull_type;
         }
-    }
-    fn print_pull_vec(pulls: &[&Pull]) {
-        let max_length = pulls.iter().map(|pull| pull.name.len()).max().unwrap() + 2;
+        if let Some(note) = note {
+            pull.set_note(note);
+        }
+        Ok(())
+    }
+
+    /// Adds a copy of `src` named `dest`, with `chance` instead of its own when given.
+    pub fn copy(
+        &mut self,
+        src: &str,
+        dest: &str,
+        chance: Option<Chance>,
+    ) -> Result<(), &'static str> {
+        if self.contains(dest) {
+            return Err("name already in list");
+        }
+        let mut pull = self.list[self.find_index(src, false)?].clone();
+        if let Some(chance) = chance {
+            pull.set_chance(chance);
+        }
+        pull.name = dest.to_owned();
+        self.list.push(pull);
+        self.invalidate_tables();
+        Ok(())
+    }
+
+    /// Enables or disables `name`, returning whether that changed anything.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<bool, &'static str> {
+        let index = self.find_index(name, false)?;
+        let pull = &mut self.list[index];
+        let changed = pull.enabled != enabled;
+        pull.enabled = enabled;
+        self.invalidate_tables();
+        Ok(changed)
+    }
+
+    /// Flips whether `name` is a favorite, returning whether it now is.
+    pub fn toggle_favorite(&mut self, name: &str) -> Result<bool, &'static str> {
+        let index = self.find_index(name, false)?;
+        let pull = &mut self.list[index];
+        pull.favorite = !pull.favorite;
+        Ok(pull.favorite)
+    }
+
+    /// Renames an item, along with its entries in the history.
+    pub fn rename(&mut self, old: &str, new: &str) -> Result<(), &'static str> {
+        if self.contains(new) {
+            return Err("name already in list");
+        }
+        let index = self.find_index(old, false)?;
+        self.list[index].name = new.to_owned();
+        self.invalidate_tables();
+        if self.rate_up.as_deref() == Some(old) {
+            self.rate_up = Some(new.to_owned());
+        }
+        for name in self.drawn.iter_mut() {
+            if name == old {
+                *name = new.to_owned();
+            }
+        }
+        for entry in self.pull_history.history.iter_mut() {
+            if entry.name == old {
+                entry.name = new.to_owned();
+            }
+        }
+        Ok(())
+    }
+
+    /// The item's chance, multiplied by `rate_up_factor` if it's the rate-up item,
+    /// or 0 when it's disabled.
+    pub fn effective_chance(&self, pull: &Pull) -> f64 {
+        if !pull.enabled {
+            0_f64
+        } else if self.rate_up.as_deref() == Some(pull.name.as_str()) {
+            pull.chance * self.rate_up_factor
+        } else {
+            pull.chance
+        }
+    }
+
+    pub fn clear_rate_up(&mut self) {
+        self.rate_up = None;
+        self.invalidate_tables();
+    }
+
+    /// Sets the rate-up item, and its factor when given.
+    pub fn set_rate_up(&mut self, name: &str, factor: Option<f64>) -> Result<(), &'static str> {
+        if !self.contains(name) {
+            return Err("not in list");
+        }
+        if let Some(factor) = factor {
+            if !factor.is_finite() || factor <= 0_f64 {
+                return Err("factor must be a finite number above 0");
+            }
+            self.rate_up_factor = factor;
+        }
+        self.rate_up = Some(name.to_owned());
+        self.invalidate_tables();
+        Ok(())
+    }
+
+    pub fn has_tier(&self, pull_type: &PullType) -> bool {
+        self.tiers.iter().any(|tier| tier.name == *pull_type)
+    }
+
+    /// Adds a tier after `after`, or as the rarest tier when `after` is `None`.
+    pub fn add_tier(
+        &mut self,
+        name: PullType,
+        rarity: usize,
+        after: Option<&PullType>,
+    ) -> Result<(), &'static str> {
+        if self.has_tier(&name) {
+            return Err("Tier already exists");
+        }
+        if rarity == 0 {
+            return Err("rarity can't be 0");
+        }
+        let index = match after {
+            Some(after) => {
+                self.tiers
+                    .iter()
+                    .position(|tier| tier.name == *after)
+                    .ok_or("Unknown tier")?
+                    + 1
+            }
+            None => self.tiers.len(),
+        };
+        if index == 0 && !name.is_common() {
+            return Err("Only common can be the first tier");
+        }
+        self.tiers.insert(index, Tier::new(name, rarity));
+        self.invalidate_tables();
+        Ok(())
+    }
+
+    /// Sets the color a tier is shown in, or resets it to the default with `None`.
+    pub fn set_tier_color(
+        &mut self,
+        pull_type: &PullType,
+        color: Option<String>,
+    ) -> Result<(), String> {
+        if let Some(color) = &color {
+            color::escape(color)?;
+        }
+        let tier = self
+            .tiers
+            .iter_mut()
+            .find(|tier| tier.name == *pull_type)
+            .ok_or_else(|| format!(r#"Unknown tier "{pull_type}""#))?;
+        tier.color = color;
+        Ok(())
+    }
+
+    pub fn tier_color(&self, pull_type: &PullType) -> Option<&str> {
+        self.tiers
+            .iter()
+            .find(|tier| tier.name == *pull_type)
+            .and_then(|tier| tier.color.as_deref())
+    }
+
+    fn tier_rarity(&self, pull_type: &PullType) -> usize {
+        self.tiers
+            .iter()
+            .find(|tier| tier.name == *pull_type)
+            .map_or(1, |tier| tier.rarity.max(1))
+    }
+
+    /// Indices into `list` grouped by tier, in tier order.
+    ///
+    /// Items whose tier isn't configured come last, in the order they appear.
+    fn tier_indices(&self) -> Vec<(PullType, Vec<usize>)> {
+        let mut groups: Vec<(PullType, Vec<usize>)> = self
+            .tiers
+            .iter()
+            .map(|tier| (tier.name.clone(), Vec::new()))
+            .collect();
+        for (index, pull) in self.list.iter().enumerate() {
+            match groups.iter_mut().find(|(name, _)| *name == pull.pull_type) {
+                Some((_, indices)) => indices.push(index),
+                None => groups.push((pull.pull_type.clone(), vec![index])),
+            }
+        }
+        groups.retain(|(_, indices)| !indices.is_empty());
+        groups
+    }
+
+    pub fn tier_groups(&self) -> Vec<(PullType, Vec<&Pull>)> {
+        self.tier_indices()
+            .into_iter()
+            .map(|(name, indices)| (name, indices.iter().map(|&i| &self.list[i]).collect()))
+            .collect()
+    }
+
+    /// Pulls a random item, drawing all randomness from `rng`.
+    ///
+    /// A tier is picked by its share of the tier weights, see `tier_weights_for`,
+    /// then an item within it by its share of the tier's chances. Pity or an
+    /// empty rare history leaves only the rare tiers to pick from.
+    ///
+    /// A seeded `rng` gives the same sequence of pulls only for the same items
+    /// and history, so adding or removing items changes the outcome. The order
+    /// items were added in doesn't, since each tier is walked by name.
+    pub fn pull<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<&Pull> {
+        self.pull_from(false, rng)
+    }
+
+    /// Pulls as if the rare branch was taken, returning `None` without rare items.
+    pub fn pull_rare<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<&Pull> {
+        self.pull_from(true, rng)
+    }
+
+    /// Pulls unless the balance can't pay the cost.
+    fn pull_from<R: Rng + ?Sized>(&mut self, rare_only: bool, rng: &mut R) -> Option<&Pull> {
+        if !self.can_afford(1) {
+            return None;
+        }
+        let (pulled_type, index) = match self.pull_mode {
+            PullMode::Replacement => self.select_cached(rare_only, rng)?,
+            PullMode::NoReplacement => self.draw(rare_only, rng)?,
+        };
+        Some(self.record(pulled_type, index))
+    }
+
+    /// `select` with the table kept from earlier pulls, building it if needed.
+    fn select_cached<R: Rng + ?Sized>(
+        &mut self,
+        rare_only: bool,
+        rng: &mut R,
+    ) -> Option<(PullType, usize)> {
+        let table = self.tables.all.take().unwrap_or_else(|| self.tier_table());
+        let selected = self.select_in(&table, rare_only, rng);
+        self.tables.all = Some(table);
+        selected
+    }
+
+    /// Picks what a pull would give without recording it.
+    pub fn peek_pull<R: Rng + ?Sized>(&self, rare_only: bool, rng: &mut R) -> Option<&Pull> {
+        let selected = match self.pull_mode {
+            PullMode::Replacement => self.select(rare_only, rng),
+            PullMode::NoReplacement => self
+                .select_in(&self.pool_table(), rare_only, rng)
+                .or_else(|| self.select(rare_only, rng)),
+        };
+        selected.map(|(_, index)| &self.list[index])
+    }
+
+    /// Picks from the items not yet drawn and marks the pick as drawn.
+    ///
+    /// When nothing is left to pick, the pool is refilled with every item first,
+    /// or with just the rare items when only those ran out for a `rare_only` pick.
+    fn draw<R: Rng + ?Sized>(&mut self, rare_only: bool, rng: &mut R) -> Option<(PullType, usize)> {
+        let pool = self.tables.pool.take().unwrap_or_else(|| self.pool_table());
+        let selected = self.select_in(&pool, rare_only, rng);
+        self.tables.pool = Some(pool);
+        let selected = match selected {
+            Some(selected) => selected,
+            None if self.drawn.is_empty() => return None,
+            None if rare_only => {
+                let common: HashSet<&str> = self
+                    .list
+                    .iter()
+                    .filter(|pull| pull.pull_type.is_common())
+                    .map(|pull| pull.name.as_str())
+                    .collect();
+                let drawn = self.drawn.len();
+                self.drawn.retain(|name| common.contains(name.as_str()));
+                if self.drawn.len() == drawn {
+                    return None;
+                }
+                let pool = self.pool_table();
+                let selected = self.select_in(&pool, rare_only, rng);
+                self.tables.pool = Some(pool);
+                selected?
+            }
+            None => {
+                self.drawn.clear();
+                self.pool_resets += 1;
+                self.tables.pool = None;
+                self.select_cached(rare_only, rng)?
+            }
+        };
+        self.mark_drawn(selected.1);
+        Some(selected)
+    }
+
+    /// Adds the item at `index` to `drawn` and takes it out of the cached pool.
+    fn mark_drawn(&mut self, index: usize) {
+        self.drawn.push(self.list[index].name.clone());
+        let mut pool = self.tables.pool.take().unwrap_or_else(|| self.pool_table());
+        self.drop_from_table(&mut pool, index);
+        self.tables.pool = Some(pool);
+    }
+
+    /// Takes the item at `index` out of `table`.
+    fn drop_from_table(&self, table: &mut [TierItems], index: usize) {
+        let pull_type = &self.list[index].pull_type;
+        let Some(tier) = table.iter_mut().find(|tier| tier.pull_type == *pull_type) else {
+            return;
+        };
+        tier.remove(index, |i| self.effective_chance(&self.list[i]));
+        if tier.sum() <= 0_f64 {
+            self.weigh_tiers(table);
+        }
+    }
+
+    /// `tier_table` without the items drawn since the pool was last refilled.
+    fn pool_table(&self) -> Vec<TierItems> {
+        let drawn: HashSet<&str> = self.drawn.iter().map(String::as_str).collect();
+        self.tier_table_where(|pull| !drawn.contains(pull.name.as_str()))
+    }
+
+    /// Sets the pull mode, refilling the pool.
+    pub fn set_pull_mode(&mut self, pull_mode: PullMode) {
+        self.pull_mode = pull_mode;
+        self.drawn.clear();
+        self.invalidate_tables();
+    }
+
+    /// Picks a tier and an index into `list` for a pull.
+    fn select<R: Rng + ?Sized>(&self, rare_only: bool, rng: &mut R) -> Option<(PullType, usize)> {
+        self.select_in(&self.tier_table(), rare_only, rng)
+    }
+
+    /// Like `select` with a `table` from `tier_table`, which stays valid until
+    /// items are added, removed, retiered or rechanced.
+    fn select_in<R: Rng + ?Sized>(
+        &self,
+        table: &[TierItems],
+        rare_only: bool,
+        rng: &mut R,
+    ) -> Option<(PullType, usize)> {
+        let tier = select_weighted(self.next_tier_weights(table, rare_only), rng)?;
+        let index = tier.select(rng)?;
+        Some((tier.pull_type.clone(), index))
+    }
+
+    /// The items of each configured tier, so repeated pulls don't have to walk
+    /// the whole list.
+    fn tier_table(&self) -> Vec<TierItems> {
+        self.tier_table_where(|_| true)
+    }
+
+    /// `tier_table` with only the enabled items `keep` returns true for.
+    ///
+    /// Each tier is sorted by name, so which item a roll lands on doesn't depend
+    /// on the order of `list`.
+    fn tier_table_where(&self, keep: impl Fn(&Pull) -> bool) -> Vec<TierItems> {
+        let mut table: Vec<TierItems> = self
+            .tier_indices()
+            .into_iter()
+            .filter(|(pull_type, _)| self.has_tier(pull_type))
+            .map(|(pull_type, mut indices)| {
+                indices.retain(|&index| self.list[index].enabled && keep(&self.list[index]));
+                indices.sort_by(|&a, &b| self.list[a].name.cmp(&self.list[b].name));
+                let cumulative = indices
+                    .iter()
+                    .scan(0_f64, |sum, &index| {
+                        *sum += self.effective_chance(&self.list[index]);
+                        Some(*sum)
+                    })
+                    .collect();
+                TierItems {
+                    pull_type,
+                    indices,
+                    cumulative,
+                    weight: 0_f64,
+                }
+            })
+            .collect();
+        self.weigh_tiers(&mut table);
+        table
+    }
+
+    /// Sets the weight of each tier in `table`.
+    fn weigh_tiers(&self, table: &mut [TierItems]) {
+        // Tiers whose chances sum to 0 can't be pulled from, so leave them to the others.
+        let tiers: Vec<&PullType> = table
+            .iter()
+            .filter(|tier| tier.sum() > 0_f64)
+            .map(|tier| &tier.pull_type)
+            .collect();
+        let mut weights = self.tier_weights_for(&tiers).into_iter();
+        for tier in table.iter_mut() {
+            tier.weight = if tier.sum() > 0_f64 {
+                weights.next().unwrap_or(0_f64)
+            } else {
+                0_f64
+            };
+        }
+    }
+
+    /// Tiers of `table` the next pull can land in with their unnormalized weights
+    /// once pity or an empty rare history has forced a rare.
+    ///
+    /// Empty when nothing can be pulled.
+    fn next_tier_weights<'a>(
+        &self,
+        table: &'a [TierItems],
+        rare_only: bool,
+    ) -> impl Iterator<Item = (&'a TierItems, f64)> + Clone {
+        let common_factor = self.common_factor(table, rare_only);
+        table
+            .iter()
+            .filter(move |tier| common_factor.is_some() && tier.sum() > 0_f64)
+            .map(move |tier| match common_factor {
+                Some(factor) if tier.pull_type.is_common() => (tier, tier.weight * factor),
+                _ => (tier, tier.weight),
+            })
+    }
+
+    /// What the next pull multiplies the common tier's weight by: 0 when a rare
+    /// is forced, less than 1 under soft pity and 1 otherwise.
+    ///
+    /// `None` when `rare_only` finds nothing rare to pull.
+    fn common_factor(&self, table: &[TierItems], rare_only: bool) -> Option<f64> {
+        let pullable = || table.iter().filter(|tier| tier.sum() > 0_f64);
+        // A forced rare falls back to a normal pull when the rare tiers all weigh 0.
+        let rare_weight: f64 = pullable()
+            .filter(|tier| !tier.pull_type.is_common())
+            .map(|tier| tier.weight)
+            .sum();
+        let forced =
+            rare_only || self.pity_counter >= self.pity_threshold || self.history_forces_rare();
+        let common_weight: f64 = pullable().map(|tier| tier.weight).sum::<f64>() - rare_weight;
+        let ramp = self.soft_pity_ramp(self.dry_streak);
+        if forced && rare_weight > 0_f64 {
+            Some(0_f64)
+        } else if rare_only {
+            None
+        } else if ramp > 0_f64 && rare_weight > 0_f64 && common_weight > 0_f64 {
+            // Shrink the common tier so the rare share becomes the soft pity boosted one.
+            let p = rare_weight / (rare_weight + common_weight);
+            let boosted = p + (1_f64 - p) * ramp;
+            Some(rare_weight * (1_f64 - boosted) / (boosted * common_weight))
+        } else {
+            Some(1_f64)
+        }
+    }
+
+    /// Chance of the next pull giving `name`, counting pity and the rare history check.
+    ///
+    /// Returns `None` when `name` isn't in the list.
+    pub fn item_odds(&self, name: &str) -> Option<f64> {
+        self.probabilities()
+            .into_iter()
+            .find(|(pull, _)| pull.name == name)
+            .map(|(_, odds)| odds)
+    }
+
+    /// `item_odds` of every item in the list: the chance of landing in its tier
+    /// times its share of the tier. These add up to 1 whenever anything can be pulled.
+    pub fn probabilities(&self) -> Vec<(&Pull, f64)> {
+        let table = self.tier_table();
+        let tiers: Vec<(&TierItems, f64)> = self.next_tier_weights(&table, false).collect();
+        let weights_sum: f64 = tiers.iter().map(|(_, weight)| weight).sum();
+        self.list
+            .iter()
+            .map(|pull| {
+                let odds = tiers
+                    .iter()
+                    .find(|(tier, _)| tier.pull_type == pull.pull_type)
+                    .map_or(0_f64, |&(tier, weight)| {
+                        share(weight, weights_sum) * self.effective_chance(pull) / tier.sum()
+                    });
+                (pull, odds)
+            })
+            .collect()
+    }
+
+    /// Unnormalized weights of landing in each of `tiers`.
+    ///
+    /// Uses `tier_weights` when any are set, where a missing tier weighs 0.
+    /// Otherwise they're derived from `rare_rarity`: the common tier weighs
+    /// `1 - 1 / rare_rarity` and the rare tiers share the rest, rolled from the
+    /// rarest down, each kept 1 in its `rarity`, with the least rare tier
+    /// taking what's left.
+    fn tier_weights_for(&self, tiers: &[&PullType]) -> Vec<f64> {
+        if !self.tier_weights.is_empty() {
+            return tiers
+                .iter()
+                .map(|pull_type| self.tier_weights.get(pull_type).copied().unwrap_or(0_f64))
+                .collect();
+        }
+        // A rarity of 0 from a hand-edited file would divide by 0, treat it as 1.
+        let rare_chance = 1_f64 / self.rare_rarity.max(1) as f64;
+        let mut weights = vec![0_f64; tiers.len()];
+        let mut remaining = if tiers.iter().any(|pull_type| pull_type.is_common()) {
+            rare_chance
+        } else {
+            1_f64
+        };
+        let rare: Vec<usize> = (0..tiers.len())
+            .filter(|&i| !tiers[i].is_common())
+            .collect();
+        for (n, &i) in rare.iter().enumerate().rev() {
+            weights[i] = if n == 0 {
+                remaining
+            } else {
+                remaining / self.tier_rarity(tiers[i]) as f64
+            };
+            remaining -= weights[i];
+        }
+        for (weight, pull_type) in weights.iter_mut().zip(tiers) {
+            if pull_type.is_common() {
+                *weight = 1_f64 - rare_chance;
+            }
+        }
+        weights
+    }
+
+    /// Chance of a pull landing in each tier that has enabled items, ignoring pity.
+    pub fn tier_chances(&self) -> Vec<(PullType, f64)> {
+        let tiers: Vec<&PullType> = self
+            .tiers
+            .iter()
+            .map(|tier| &tier.name)
+            .filter(|name| {
+                self.list
+                    .iter()
+                    .any(|pull| pull.enabled && pull.pull_type == **name)
+            })
+            .collect();
+        let weights = self.tier_weights_for(&tiers);
+        let weights_sum: f64 = weights.iter().sum();
+        tiers
+            .into_iter()
+            .zip(weights)
+            .map(|(pull_type, weight)| (pull_type.clone(), share(weight, weights_sum)))
+            .collect()
+    }
+
+    /// Sets the weight of landing in `pull_type`, or removes it with `None`.
+    pub fn set_tier_weight(
+        &mut self,
+        pull_type: &PullType,
+        weight: Option<f64>,
+    ) -> Result<(), &'static str> {
+        if !self.has_tier(pull_type) {
+            return Err("tier doesn't exist");
+        }
+        match weight {
+            Some(weight) if !weight.is_finite() || weight < 0_f64 => {
+                Err("weight must be a finite number of at least 0")
+            }
+            Some(weight) => {
+                self.tier_weights.insert(pull_type.clone(), weight);
+                self.invalidate_tables();
+                Ok(())
+            }
+            None => {
+                self.tier_weights.remove(pull_type);
+                self.invalidate_tables();
+                Ok(())
+            }
+        }
+    }
+
+    /// Updates the counters and history for a pull of `list[index]`.
+    fn record(&mut self, pulled_type: PullType, index: usize) -> &Pull {
+        if pulled_type.is_common() {
+            self.pity_counter += 1;
+            self.dry_streak += 1;
+        } else {
+            self.pity_counter = 0;
+            self.dry_streak = 0;
+        }
+        self.total_pulls += 1;
+        self.balance -= self.cost;
+        self.last_pull_id += 1;
+        self.pull_history.update(
+            self.last_pull_id,
+            pulled_type,
+            self.list[index].name.clone(),
+            self.banner.clone(),
+            self.cost,
+        );
+        &self.list[index]
+    }
+
+    /// Whether the balance pays for `count` pulls.
+    pub fn can_afford(&self, count: u64) -> bool {
+        self.cost
+            .checked_mul(count)
+            .is_some_and(|total| total <= self.balance)
+    }
+
+    /// Time left until the cooldown allows the next pull, `None` when it already does.
+    pub fn cooldown_remaining(&self) -> Option<chrono::Duration> {
+        let secs = self.cooldown_secs?;
+        let last = self.pull_history.last_pull_time()?;
+        let next = i64::try_from(secs)
+            .ok()
+            .and_then(|secs| secs.checked_mul(1000))
+            .and_then(|millis| last.checked_add_signed(chrono::Duration::milliseconds(millis)));
+        // A cooldown too long to end on a date never ends.
+        let Some(next) = next else {
+            return Some(chrono::Duration::milliseconds(i64::MAX));
+        };
+        let remaining = next.signed_duration_since(Local::now());
+        (remaining > chrono::Duration::zero()).then_some(remaining)
+    }
+
+    /// Raises rarities of 0, which only a hand-edited file can have, to 1.
+    ///
+    /// Returns a message for each one raised.
+    pub fn fix_rarities(&mut self) -> Vec<String> {
+        let mut fixed = Vec::new();
+        if self.rare_rarity == 0 {
+            self.rare_rarity = 1;
+            fixed.push("rare rarity was 0, using 1 instead".to_owned());
+        }
+        for tier in self.tiers.iter_mut().filter(|tier| tier.rarity == 0) {
+            tier.rarity = 1;
+            fixed.push(format!("{} tier rarity was 0, using 1 instead", tier.name));
+        }
+        self.invalidate_tables();
+        fixed
+    }
+
+    /// Sets the banner name, filling it in as the source of older history entries.
+    pub fn set_banner(&mut self, name: &str) {
+        for entry in self.pull_history.history.iter_mut() {
+            if entry.source.is_empty() {
+                entry.source = name.to_owned();
+            }
+        }
+        self.banner = name.to_owned();
+    }
+
+    /// Gives history entries saved before pulls were numbered, or merged in from
+    /// another list, the next `pull_id`s.
+    pub fn number_pulls(&mut self) {
+        for entry in self.pull_history.history.iter_mut() {
+            if entry.pull_id == 0 {
+                self.last_pull_id += 1;
+                entry.pull_id = self.last_pull_id;
+            }
+        }
+    }
+
+    pub fn has_rare(&self) -> bool {
+        self.list.iter().any(|pull| {
+            pull.enabled && !pull.pull_type.is_common() && self.has_tier(&pull.pull_type)
+        })
+    }
+
+    /// Undoes the last pull, rolling the pity counter and dry streak back with it.
+    pub fn undo(&mut self) -> Option<HistoryEntry> {
+        let entry = self.pull_history.undo()?;
+        if self.drawn.last() == Some(&entry.name) {
+            self.drawn.pop();
+            self.tables.pool = None;
+        }
+        self.roll_back(&entry, true);
+        Some(entry)
+    }
+
+    /// Undoes the pull numbered `pull_id` if it's still in the history.
+    ///
+    /// The pity counter and dry streak only change when no rare was pulled after it.
+    pub fn undo_id(&mut self, pull_id: u64) -> Option<HistoryEntry> {
+        let history = &mut self.pull_history.history;
+        let index = history.iter().position(|entry| entry.pull_id == pull_id)?;
+        let in_streak = history
+            .range(index + 1..)
+            .all(|later| later.pull_type.is_common());
+        let entry = history.remove(index)?;
+        if let Some(index) = self.drawn.iter().rposition(|name| *name == entry.name) {
+            self.drawn.remove(index);
+            self.tables.pool = None;
+        }
+        self.roll_back(&entry, in_streak);
+        Some(entry)
+    }
+
+    /// Takes an undone pull out of the totals, and out of the pity counter and
+    /// dry streak when `in_streak`.
+    fn roll_back(&mut self, entry: &HistoryEntry, in_streak: bool) {
+        self.total_pulls = self.total_pulls.saturating_sub(1);
+        self.balance = self.balance.saturating_add(entry.cost);
+        if !in_streak {
+            return;
+        }
+        if entry.pull_type.is_common() {
+            self.pity_counter = self.pity_counter.saturating_sub(1);
+            self.dry_streak = self.dry_streak.saturating_sub(1);
+        } else {
+            let since_rare = self
+                .pull_history
+                .history
+                .iter()
+                .rev()
+                .take_while(|entry| entry.pull_type.is_common())
+                .count();
+            self.pity_counter = since_rare;
+            self.dry_streak = since_rare;
+        }
+    }
+
+    /// Expected number of pulls until any non-common item is pulled.
+    ///
+    /// Each pull is rare with the rare tiers' share `p` of `tier_chances`, raised by
+    /// soft pity, until pity or the last rare leaving the history forces one on pull `k`.
+    /// This sums the chance of still being dry before each of those pulls, which
+    /// without soft pity is the capped geometric mean `(1 - (1 - p)^k) / p`.
+    /// Returns infinity when there are no rare items.
+    pub fn expected_pulls_until_rare(&self) -> f64 {
+        let has_common = self.list.iter().any(|pull| pull.pull_type.is_common());
+        if !self.has_rare() {
+            return f64::INFINITY;
+        }
+        if !has_common || self.history_forces_rare() {
+            return 1_f64;
+        }
+        let since_rare = self
+            .pull_history
+            .history
+            .iter()
+            .rev()
+            .take_while(|entry| entry.pull_type.is_common())
+            .count();
+        let forced_by_history = if self.guarantee_first_rare {
+            self.pull_history.size.saturating_sub(since_rare) + 1
+        } else {
+            usize::MAX
+        };
+        let forced_by_pity = self.pity_threshold.saturating_sub(self.pity_counter) + 1;
+        let k = forced_by_history.min(forced_by_pity);
+
+        let p = self.rare_chance();
+        let mut expected = 0_f64;
+        let mut dry = 1_f64;
+        for pull in 0..k {
+            expected += dry;
+            let ramp = self.soft_pity_ramp(self.dry_streak + pull);
+            dry *= (1_f64 - p) * (1_f64 - ramp);
+        }
+        expected
+    }
+
+    /// Whether the next pull is rare because no rare is left in the history,
+    /// see `guarantee_first_rare`.
+    pub fn history_forces_rare(&self) -> bool {
+        self.guarantee_first_rare && !self.pull_history.contains_rare()
+    }
+
+    /// How far soft pity has pushed the rare chance toward certainty at `streak`.
+    ///
+    /// Rises linearly from 0 at `soft_pity_start` to 1 at `pity_threshold`:
+    /// `(streak - start) / (threshold - start)`, and is 0 without soft pity.
+    pub fn soft_pity_ramp(&self, streak: usize) -> f64 {
+        match self.soft_pity_start {
+            Some(start) if streak > start && self.pity_threshold > start => {
+                ((streak - start) as f64 / (self.pity_threshold - start) as f64).min(1_f64)
+            }
+            _ => 0_f64,
+        }
+    }
+
+    /// Sets the dry streak soft pity starts at, or turns it off with `None`.
+    pub fn set_soft_pity(&mut self, start: Option<usize>) -> Result<(), &'static str> {
+        if start.is_some_and(|start| start >= self.pity_threshold) {
+            return Err("soft pity has to start before the pity threshold");
+        }
+        self.soft_pity_start = start;
+        Ok(())
+    }
+
+    /// Chance of a pull landing in any rare tier, ignoring pity.
+    pub fn rare_chance(&self) -> f64 {
+        self.tier_chances()
+            .iter()
+            .filter(|(pull_type, _)| !pull_type.is_common())
+            .map(|(_, chance)| chance)
+            .sum()
+    }
+
+    /// A warning when rares are pulled less than `LOW_RARE_CHANCE` of the time
+    /// outside of pity.
+    pub fn low_rare_chance(&self) -> Option<ValidationIssue> {
+        let has_common = self.list.iter().any(|pull| pull.pull_type.is_common());
+        let chance = self.rare_chance();
+        if !has_common || chance <= 0_f64 || chance >= LOW_RARE_CHANCE {
+            return None;
+        }
+        Some(ValidationIssue::warning(format!(
+            "rares are only pulled {:.4}% of the time outside of pity, check rare_rarity",
+            chance * 100_f64
+        )))
+    }
+
+    /// Checks the list for problems the pull logic would silently work around.
+    ///
+    /// Bad chances and duplicate names are errors, everything else is a warning.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let mut seen = HashSet::new();
+        for pull in &self.list {
+            if let Err(err) = check_chance(pull.chance) {
+                issues.push(ValidationIssue::error(format!(
+                    r#""{}" has chance {}, {err}"#,
+                    pull.name, pull.chance
+                )));
+            }
+            if !seen.insert(pull.name.as_str()) {
+                issues.push(ValidationIssue::error(format!(
+                    r#""{}" is listed more than once"#,
+                    pull.name
+                )));
+            }
+            if !self.has_tier(&pull.pull_type) {
+                issues.push(ValidationIssue::warning(format!(
+                    r#""{}" is in unknown tier "{}" and is never pulled"#,
+                    pull.name, pull.pull_type
+                )));
+            }
+        }
+        for tier in &self.tiers {
+            if !self.list.iter().any(|pull| pull.pull_type == tier.name) {
+                issues.push(ValidationIssue::warning(format!(
+                    "{} tier has no items",
+                    tier.name
+                )));
+            }
+        }
+        if !self.list.is_empty() && !self.has_rare() {
+            issues.push(ValidationIssue::warning(format!(
+                "no rare items, the 1 in {} rare chance never applies",
+                self.rare_rarity
+            )));
+        } else if self.has_rare() && !self.list.iter().any(|pull| pull.pull_type.is_common()) {
+            issues.push(ValidationIssue::warning(
+                "no common items, every pull is rare".to_owned(),
+            ));
+        }
+        if let Some(rate_up) = &self.rate_up {
+            if !self.contains(rate_up) {
+                issues.push(ValidationIssue::warning(format!(
+                    r#"rate-up item "{rate_up}" is not in the list"#
+                )));
+            }
+        }
+        for (pull_type, chance) in self.tier_chances() {
+            if chance <= 0_f64 {
+                issues.push(ValidationIssue::warning(format!(
+                    "{pull_type} tier has items but a 0% chance of being pulled"
+                )));
+            }
+        }
+        issues.extend(self.low_rare_chance());
+        issues
+    }
+
+    /// Pulls `n` times from a copy of the list and counts the hits per item name.
+    pub fn simulate<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> HashMap<String, usize> {
+        let mut pull_list = self.clone();
+        pull_list.cost = 0;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..n {
+            let selected = match pull_list.pull_mode {
+                PullMode::Replacement => pull_list.select_cached(false, rng),
+                PullMode::NoReplacement => pull_list.draw(false, rng),
+            };
+            let Some((pulled_type, index)) = selected else {
+                break;
+            };
+            let pull = pull_list.record(pulled_type, index);
+            match counts.get_mut(&pull.name) {
+                Some(count) => *count += 1,
+                None => {
+                    counts.insert(pull.name.clone(), 1);
+                }
+            }
+        }
+        counts
+    }
+
+    /// Multiplies the chance of every item, or only those in `tier`, by `factor`.
+    pub fn scale(&mut self, factor: f64, tier: Option<&PullType>) -> Result<(), &'static str> {
+        if !factor.is_finite() || factor <= 0_f64 {
+            return Err("factor must be a finite number above 0");
+        }
+        for pull in &mut self.list {
+            if tier.is_none_or(|tier| pull.pull_type == *tier) {
+                pull.chance *= factor;
+            }
+        }
+        self.invalidate_tables();
+        Ok(())
+    }
+
+    /// Rounds every chance to `places` decimals, returning how many changed.
+    ///
+    /// Nothing is rounded when that would leave a chance at 0.
+    pub fn round(&mut self, places: u32) -> Result<usize, &'static str> {
+        if places > MAX_ROUND_PLACES {
+            return Err("places must be between 0 and 10");
+        }
+        let factor = 10_f64.powi(places as i32);
+        let rounded: Vec<f64> = self
+            .list
+            .iter()
+            .map(|pull| {
+                let scaled = pull.chance * factor;
+                // Chances too large to scale have no decimals left to round.
+                if scaled.is_finite() {
+                    scaled.round() / factor
+                } else {
+                    pull.chance
+                }
+            })
+            .collect();
+        if rounded.iter().any(|&chance| chance <= 0_f64) {
+            return Err("that would round a chance down to 0");
+        }
+        let mut changed = 0;
+        for (pull, chance) in self.list.iter_mut().zip(rounded) {
+            if pull.chance != chance {
+                pull.chance = chance;
+                changed += 1;
+            }
+        }
+        self.invalidate_tables();
+        Ok(changed)
+    }
+
+    /// Adds the items of `other`, resolving name clashes with `policy`.
+    ///
+    /// Tiers missing from this list are added as the rarest. With `history`,
+    /// the other history is merged in by timestamp, keeping the newest entries.
+    pub fn merge(
+        &mut self,
+        other: PullList,
+        policy: ConflictPolicy,
+        history: bool,
+    ) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+        for tier in other.tiers {
+            if !self.has_tier(&tier.name) {
+                self.tiers.push(tier);
+                summary.tiers_added += 1;
+            }
+        }
+        let mut renames = HashMap::new();
+        for mut pull in other.list {
+            if !self.contains(&pull.name) {
+                self.list.push(pull);
+                summary.added += 1;
+                continue;
+            }
+            match policy {
+                ConflictPolicy::Skip => summary.skipped += 1,
+                ConflictPolicy::Overwrite => {
+                    self.replace(pull);
+                    summary.overwritten += 1;
+                }
+                ConflictPolicy::Rename => {
+                    let name = (2..)
+                        .map(|n| format!("{} ({n})", pull.name))
+                        .find(|name| !self.contains(name))
+                        .unwrap();
+                    renames.insert(std::mem::replace(&mut pull.name, name.clone()), name);
+                    self.list.push(pull);
+                    summary.renamed += 1;
+                }
+            }
+        }
+        if history {
+            let entries = &mut self.pull_history.history;
+            entries.extend(other.pull_history.history.into_iter().map(|mut entry| {
+                if let Some(name) = renames.get(&entry.name) {
+                    entry.name = name.clone();
+                }
+                // The other list numbered its pulls separately, so they get new numbers.
+                entry.pull_id = 0;
+                entry
+            }));
+            entries
+                .make_contiguous()
+                .sort_by_key(|entry| entry.date_time);
+            while entries.len() > self.pull_history.size {
+                entries.pop_front();
+            }
+            self.number_pulls();
+        }
+        self.invalidate_tables();
+        summary
+    }
+
+    /// Rescales chances so each tier sums to 100, keeping their proportions.
+    pub fn normalize(&mut self) {
+        for (_, indices) in self.tier_indices() {
+            let sum: f64 = indices.iter().map(|&i| self.list[i].chance).sum();
+            if sum > 0_f64 {
+                for i in indices {
+                    self.list[i].chance = self.list[i].chance / sum * 100_f64;
+                }
+            }
+        }
+        self.invalidate_tables();
+    }
+
+    /// Clears the history along with the pity counter and dry streak that depend on it.
+    pub fn clear_history(&mut self) {
+        self.pull_history.clear();
+        self.pity_counter = 0;
+        self.dry_streak = 0;
+    }
+
+    pub fn pull_many<R: Rng + ?Sized>(
+        &mut self,
+        count: usize,
+        rare_only: bool,
+        rng: &mut R,
+    ) -> Vec<Pull> {
+        if self.list.is_empty() {
+            return Vec::new();
+        }
+        (0..count)
+            .map_while(|_| self.pull_from(rare_only, rng).cloned())
+            .collect()
+    }
+
+    /// Pulls `count` items without repeating one until every item has been
+    /// pulled in this batch, then starts a new round with every item again.
+    pub fn pull_step_up<R: Rng + ?Sized>(&mut self, count: usize, rng: &mut R) -> Vec<Pull> {
+        // The items not yet pulled this round, cut down as the round goes.
+        let mut unseen: Option<Vec<TierItems>> = None;
+        let mut pulls = Vec::new();
+        for _ in 0..count {
+            if !self.can_afford(1) {
+                break;
+            }
+            let table = unseen.take().unwrap_or_else(|| self.step_up_table());
+            let selected = match self.select_in(&table, false, rng) {
+                Some(selected) => {
+                    if self.pull_mode == PullMode::NoReplacement {
+                        self.mark_drawn(selected.1);
+                    }
+                    unseen = Some(table);
+                    Some(selected)
+                }
+                None => match self.pull_mode {
+                    PullMode::Replacement => self.select_cached(false, rng),
+                    PullMode::NoReplacement => self.draw(false, rng),
+                },
+            };
+            let Some((pulled_type, index)) = selected else {
+                break;
+            };
+            let mut table = unseen.take().unwrap_or_else(|| self.step_up_table());
+            self.drop_from_table(&mut table, index);
+            unseen = Some(table);
+            pulls.push(self.record(pulled_type, index).clone());
+        }
+        pulls
+    }
+
+    /// The table a step-up round starts from: everything, or what's left in
+    /// the pool.
+    fn step_up_table(&mut self) -> Vec<TierItems> {
+        match self.pull_mode {
+            PullMode::Replacement => {
+                let table = self.tables.all.take().unwrap_or_else(|| self.tier_table());
+                self.tables.all = Some(table.clone());
+                table
+            }
+            PullMode::NoReplacement => self
+                .tables
+                .pool
+                .clone()
+                .unwrap_or_else(|| self.pool_table()),
+        }
+    }
+
+    /// Pulls ten items, forcing the tenth to be rare when the first nine weren't.
+    pub fn pull_ten<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Vec<Pull> {
+        let mut pulls = self.pull_many(9, false, rng);
+        let forced = if pulls.iter().all(|pull| pull.pull_type.is_common()) {
+            self.pull_rare(rng).cloned()
+        } else {
+            None
+        };
+        let tenth = forced.or_else(|| self.pull(rng).cloned());
+        pulls.extend(tenth);
+        pulls
+    }
+
+    /// Writes the list as CSV with a `name,pull_type,chance` header.
+    pub fn export_csv(&self, mut writer: impl Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(writer, "name,pull_type,chance")?;
+        for pull in &self.list {
+            let chance = pull.chance_text();
+            writeln!(
+                writer,
+                "{}",
+                csv::record(&[&pull.name, pull.pull_type.name(), &chance])
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Inserts every `name,pull_type,chance` row read from `reader`,
+    /// returning how many were added. With `replace` the rows take the place
+    /// of the whole list.
+    ///
+    /// Nothing is changed unless every row is valid.
+    pub fn import_csv(
+        &mut self,
+        mut reader: impl Read,
+        replace: bool,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut pulls: Vec<Pull> = Vec::new();
+        for (line, fields) in csv::parse(&text)? {
+            if line == 1 && fields == ["name", "pull_type", "chance"] {
+                continue;
+            }
+            let pull = self
+                .parse_csv_row(&fields)
+                .map_err(|err| format!("line {line}: {err}"))?;
+            let exists = !replace && self.contains(&pull.name);
+            if exists || pulls.iter().any(|p| p.name == pull.name) {
+                return Err(format!(r#"line {line}: "{}" already exists"#, pull.name).into());
+            }
+            pulls.push(pull);
+        }
+        let count = pulls.len();
+        if replace {
+            self.list.clear();
+        }
+        self.list.extend(pulls);
+        self.invalidate_tables();
+        Ok(count)
+    }
+
+    /// Inserts a `name:pull_type:chance` item per line, skipping blank lines and
+    /// `#` comments. Returns how many were added and why each other line was skipped.
+    pub fn add_lines(&mut self, text: &str) -> (usize, Vec<String>) {
+        let mut added = 0;
+        let mut skipped = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let result = Pull::from_str(line).and_then(|pull| {
+                if !self.has_tier(&pull.pull_type) {
+                    Err("unknown tier")
+                } else if !self.insert(pull) {
+                    Err("item already exists")
+                } else {
+                    Ok(())
+                }
+            });
+            match result {
+                Ok(()) => added += 1,
+                Err(err) => skipped.push(format!("line {}: {err}", index + 1)),
+            }
+        }
+        (added, skipped)
+    }
+
+    /// Inserts `count` items named `prefix_1`, `prefix_2`, etc. with the same tier and
+    /// chance. Names already in the list are skipped; returns how many were added.
+    pub fn add_many(
+        &mut self,
+        prefix: &str,
+        pull_type: &PullType,
+        chance: Chance,
+        count: usize,
+    ) -> Result<usize, &'static str> {
+        if !self.has_tier(pull_type) {
+            return Err("unknown tier");
+        }
+        if count == 0 || count > MAX_ADD_MANY {
+            return Err("count must be between 1 and 10000");
+        }
+        let mut added = 0;
+        for index in 1..=count {
+            if self.insert(Pull::with_chance(
+                format!("{prefix}_{index}"),
+                pull_type.clone(),
+                chance,
+            )) {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    fn parse_csv_row(&self, fields: &[String]) -> Result<Pull, &'static str> {
+        let [name, pull_type, chance] = fields else {
+            return Err("expected name,pull_type,chance");
+        };
+        let pull_type = PullType::from_str(pull_type)?;
+        if !self.has_tier(&pull_type) {
+            return Err("unknown tier");
+        }
+        let chance = Chance::from_str(chance)?;
+        Ok(Pull::with_chance(name.to_owned(), pull_type, chance))
+    }
+
+    /// A Markdown table of every item with its chance within its tier and overall,
+    /// ignoring pity, followed by the rarity settings and expected pulls to a rare.
+    pub fn report_markdown(&self) -> String {
+        let tier_chances = self.tier_chances();
+        let mut report = format!("## Banner `{}`\n\n", self.banner);
+        report.push_str("| Item | Tier | Chance | In tier | Overall |\n");
+        report.push_str("| ---- | ---- | -----: | ------: | ------: |\n");
+        for (pull_type, pulls) in self.tier_groups() {
+            let tier_sum = self.weight_sum(&pulls);
+            let tier_chance = tier_chances
+                .iter()
+                .find(|(name, _)| *name == pull_type)
+                .map_or(0_f64, |(_, chance)| *chance);
+            for pull in pulls {
+                let in_tier = share(self.effective_chance(pull), tier_sum);
+                report.push_str(&format!(
+                    "| {} | {pull_type} | {} | {:.2}% | {:.2}% |\n",
+                    pull.name.replace('|', "\\|"),
+                    pull.rounded_chance_text(DEFAULT_PRECISION),
+                    in_tier * 100_f64,
+                    in_tier * tier_chance * 100_f64
+                ));
+            }
+        }
+        report.push('\n');
+        if self.tier_weights.is_empty() {
+            report.push_str(&format!("- Rare rarity: 1 in {}\n", self.rare_rarity));
+        }
+        let chances: Vec<String> = tier_chances
+            .iter()
+            .map(|(pull_type, chance)| format!("{pull_type} {:.2}%", chance * 100_f64))
+            .collect();
+        report.push_str(&format!("- Tier chances: {}\n", chances.join(", ")));
+        report.push_str(&format!("- Pity: {} pulls\n", self.pity_threshold));
+        let expected = self.expected_pulls_until_rare();
+        if expected.is_finite() {
+            report.push_str(&format!("- Expected pulls until a rare: {expected:.2}\n"));
+        } else {
+            report.push_str("- Expected pulls until a rare: never, no rare items\n");
+        }
+        report
+    }
+
+    pub fn print_list(&self, options: &ListOptions) {
+        if self.list.is_empty() {
+            println!("No items to list");
+            return;
+        }
+        println!("Current dry streak: {}", self.dry_streak);
+        let groups = self.tier_groups();
+        let tier_sum = |pull_type: &PullType| {
+            groups
+                .iter()
+                .find(|(name, _)| name == pull_type)
+                .map_or(0_f64, |(_, pulls)| self.weight_sum(pulls))
+        };
+        let mut pulls = match &options.tag {
+            Some(tag) => self.filter_by_tag(tag),
+            None => self.list.iter().collect(),
+        };
+        if let Some(pull_type) = &options.pull_type {
+            pulls.retain(|pull| pull.pull_type == *pull_type);
+            if pulls.is_empty() {
+                println!("No {pull_type} pulls.");
+                return;
+            }
+        }
+        if let (Some(tag), true) = (&options.tag, pulls.is_empty()) {
+            println!(r#"No items tagged "{tag}"."#);
+            return;
+        }
+        let rows: Vec<&Pull> = if options.flat {
+            let mut pulls = pulls;
+            if let Some(sort) = options.sort {
+                sort.sort(&mut pulls, options.desc);
+            }
+            pulls
+        } else {
+            groups
+                .iter()
+                .flat_map(|(pull_type, _)| {
+                    let mut tier: Vec<&Pull> = pulls
+                        .iter()
+                        .copied()
+                        .filter(|pull| pull.pull_type == *pull_type)
+                        .collect();
+                    if let Some(sort) = options.sort {
+                        sort.sort(&mut tier, options.desc);
+                    }
+                    tier
+                })
+                .collect()
+        };
+
+        let pages = rows.len().div_ceil(options.per_page.max(1));
+        let rows = match options.page {
+            Some(page) if page == 0 || page > pages => {
+                println!("Page {page} doesn't exist, there are {pages} pages.");
+                return;
+            }
+            Some(page) => {
+                let start = (page - 1) * options.per_page;
+                &rows[start..(start + options.per_page).min(rows.len())]
+            }
+            None => &rows[..],
+        };
+
+        if options.flat {
+            let max_length = rows
+                .iter()
+                .map(|pull| pull.label().chars().count())
+                .max()
+                .unwrap_or(0);
+            for pull in rows {
+                println!(
+                    "{:<max_length$} : {} ({:.2}%) {}",
+                    pull.label(),
+                    pull.rounded_chance_text(options.precision),
+                    share(self.effective_chance(pull), tier_sum(&pull.pull_type)) * 100_f64,
+                    pull.pull_type
+                );
+                if options.notes {
+                    print_note(pull);
+                }
+            }
+        } else {
+            for chunk in rows.chunk_by(|a, b| a.pull_type == b.pull_type) {
+                let pull_type = &chunk[0].pull_type;
+                let tier_sum = tier_sum(pull_type);
+                println!("-{pull_type} Pulls-");
+                self.print_pull_vec(chunk, tier_sum, options.precision, options.notes);
+                println!(
+                    "Total weight: {}",
+                    format_chance(tier_sum, options.precision)
+                );
+            }
+        }
+        if let Some(page) = options.page {
+            println!("Page {page}/{pages}");
+        }
+        if let Some(rate_up) = &self.rate_up {
+            println!("Rate-up: {rate_up:?} x{}", self.rate_up_factor);
+        }
+        if self.cost > 0 || self.balance > 0 {
+            println!("Balance: {} ({} per pull)", self.balance, self.cost);
+        }
+        if self.pull_mode == PullMode::NoReplacement {
+            // Disabled items can't be drawn, so they aren't part of the pool.
+            let enabled: Vec<&Pull> = self.list.iter().filter(|pull| pull.enabled).collect();
+            let left = enabled
+                .iter()
+                .filter(|pull| !self.drawn.contains(&pull.name))
+                .count();
+            println!("Pool: {left}/{} left", enabled.len());
+        }
+        match self.pull_history.last_pull_time() {
+            Some(last) => println!(
+                "Last pull: {} ago",
+                format_elapsed(Local::now().signed_duration_since(last))
+            ),
+            None => println!("Last pull: never"),
+        }
+        println!("Pity: {}/{}", self.pity_counter, self.pity_threshold);
+        println!("Total pulls: {}", self.total_pulls);
+        let totals: Vec<String> = groups
+            .iter()
+            .map(|(pull_type, pulls)| {
+                format!(
+                    "{pull_type}: {} item{}, total weight {}",
+                    pulls.len(),
+                    if pulls.len() == 1 { "" } else { "s" },
+                    format_chance(self.weight_sum(pulls), options.precision)
+                )
+            })
+            .collect();
+        println!("{}", totals.join(" | "));
+    }
+
+    /// `rare_message` filled in for `pull`, or `None` if it isn't set or `pull` isn't rare.
+    pub fn rare_announcement(&self, pull: &Pull) -> Option<String> {
+        if pull.pull_type.is_common() {
+            return None;
+        }
+        let message = self.rare_message.as_ref()?;
+        Some(
+            message
+                .replace("{name}", &pull.name)
+                .replace("{chance}", &pull.chance_text()),
+        )
+    }
+
+    /// Prints the items nested by tier, then tag, with untagged items last.
+    ///
+    /// Items with several tags are shown under each of them.
+    pub fn print_tree(&self) {
+        for (pull_type, pulls) in self.tier_groups() {
+            println!("{pull_type}");
+            let mut tags: BTreeMap<&str, Vec<&Pull>> = BTreeMap::new();
+            let mut untagged = Vec::new();
+            for pull in pulls {
+                if pull.tags.is_empty() {
+                    untagged.push(pull);
+                }
+                for tag in &pull.tags {
+                    tags.entry(tag).or_default().push(pull);
+                }
+            }
+            let nodes = tags
+                .into_iter()
+                .chain((!untagged.is_empty()).then_some(("(untagged)", untagged)));
+            for (tag, pulls) in nodes {
+                println!("  {tag}");
+                for pull in pulls {
+                    println!(
+                        "    {} : {}",
+                        pull.label(),
+                        pull.rounded_chance_text(DEFAULT_PRECISION)
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn filter_by_tag(&self, tag: &str) -> Vec<&Pull> {
+        self.list.iter().filter(|pull| pull.has_tag(tag)).collect()
+    }
+
+    /// Items whose name contains `query`, ignoring case.
+    pub fn find(&self, query: &str) -> Vec<&Pull> {
+        let query = query.to_lowercase();
+        self.list
+            .iter()
+            .filter(|pull| pull.name.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// Prints some of the items grouped by tier, with percentages of their whole tier.
+    pub fn print_pulls(&self, pulls: &[&Pull]) {
+        for (pull_type, tier) in self.tier_groups() {
+            let matches: Vec<&Pull> = pulls
+                .iter()
+                .copied()
+                .filter(|pull| pull.pull_type == pull_type)
+                .collect();
+            if !matches.is_empty() {
+                println!("-{pull_type} Pulls-");
+                self.print_pull_vec(&matches, self.weight_sum(&tier), DEFAULT_PRECISION, false);
+            }
+        }
+    }
+
+    fn weight_sum(&self, pulls: &[&Pull]) -> f64 {
+        pulls.iter().map(|pull| self.effective_chance(pull)).sum()
+    }
+
+    fn print_pull_vec(&self, pulls: &[&Pull], tier_sum: f64, precision: usize, notes: bool) {
+        let max_length = pulls
+            .iter()
+            .map(|pull| pull.label().chars().count())
+            .max()
+            .unwrap();
         for pull in pulls.iter() {
             println!(
-                "{:<max_length$} : {}",
-                format!("\"{}\"", pull.name),
-                pull.chance
+                "{:<max_length$} : {} ({:.2}%)",
+                pull.label(),
+                pull.rounded_chance_text(precision),
+                share(self.effective_chance(pull), tier_sum) * 100_f64
+            );
+            if notes {
+                print_note(pull);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// A list of `count` common items with uneven chances.
+    fn common_list(count: usize) -> PullList {
+        let mut pull_list = PullList::new();
+        for i in 0..count {
+            let chance = (i % 7) as f64 * 0.3 + 0.1;
+            pull_list.insert(Pull::new(format!("Item {i}"), PullType::common(), chance));
+        }
+        pull_list
+    }
+
+    #[test]
+    fn binary_search_selection_matches_linear_selection() {
+        let pull_list = common_list(50);
+        let table = pull_list.tier_table();
+        let tier = &table[0];
+        for seed in 0..1_000 {
+            let weights = tier
+                .indices
+                .iter()
+                .map(|&index| (index, pull_list.effective_chance(&pull_list.list[index])));
+            let linear = select_weighted(weights, &mut StdRng::seed_from_u64(seed));
+            let binary = tier.select(&mut StdRng::seed_from_u64(seed));
+            assert_eq!(binary, linear, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn history_keeps_exactly_size_entries() {
+        let mut history = PullHistory::new(3);
+        for i in 0..3 {
+            history.update(i, PullType::common(), format!("Item {i}"), String::new(), 0);
+        }
+        assert_eq!(history.history.len(), 3);
+        assert_eq!(history.history[0].name, "Item 0");
+
+        history.update(3, PullType::common(), "Item 3".to_owned(), String::new(), 0);
+        assert_eq!(history.history.len(), 3);
+        assert_eq!(history.history[0].name, "Item 1");
+    }
+
+    #[test]
+    fn check_chance_rejects_non_finite_chances() {
+        for chance in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(check_chance(chance).is_err(), "{chance}");
+        }
+    }
+
+    #[test]
+    fn chance_from_str_rejects_non_finite_chances() {
+        for text in [
+            "NaN", "inf", "-inf", "infinity", "1e999", "NaN%", "inf%", "-inf%",
+        ] {
+            assert!(Chance::from_str(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn select_weighted_handles_near_zero_sums() {
+        let tiny = [
+            (0, 1e-300),
+            (1, 1e-300),
+            (2, f64::MIN_POSITIVE),
+            (3, 5e-324),
+        ];
+        for seed in 0..1_000 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            assert!(select_weighted(tiny.iter().copied(), &mut rng).is_some());
+        }
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            select_weighted([(0, 0_f64), (1, 0_f64)].into_iter(), &mut rng),
+            None
+        );
+        assert_eq!(
+            select_weighted([(0, f64::MAX), (1, f64::MAX)].into_iter(), &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn pulls_from_near_zero_sum_tiers() {
+        let mut pull_list = PullList::new();
+        pull_list.insert(Pull::new("Tiny".to_owned(), PullType::common(), 5e-324));
+        pull_list.insert(Pull::new("Small".to_owned(), PullType::common(), 1e-300));
+        pull_list.insert(Pull::new("Rare".to_owned(), PullType::rare(), 1e-300));
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1_000 {
+            let table = pull_list.tier_table();
+            assert_eq!(pull_list.next_tier_weights(&table, false).count(), 2);
+            assert!(pull_list.pull(&mut rng).is_some());
+        }
+    }
+
+    #[test]
+    fn soft_pity_ramp_rises_linearly() {
+        let mut pull_list = PullList::new();
+        pull_list.pity_threshold = 90;
+        assert_eq!(pull_list.soft_pity_ramp(80), 0_f64);
+
+        pull_list.set_soft_pity(Some(70)).unwrap();
+        for (streak, ramp) in [
+            (0, 0.0),
+            (70, 0.0),
+            (75, 0.25),
+            (80, 0.5),
+            (90, 1.0),
+            (200, 1.0),
+        ] {
+            assert!(
+                (pull_list.soft_pity_ramp(streak) - ramp).abs() < 1e-12,
+                "streak {streak}"
             );
         }
     }
+
+    #[test]
+    fn soft_pity_boosts_the_rare_chance() {
+        let mut pull_list = common_list(5);
+        pull_list.insert(Pull::new("Rare".to_owned(), PullType::rare(), 1_f64));
+        pull_list.guarantee_first_rare = false;
+        pull_list.pity_threshold = 90;
+        pull_list.set_soft_pity(Some(70)).unwrap();
+        let rare_chance = |pull_list: &PullList| {
+            let table = pull_list.tier_table();
+            let tiers = pull_list.next_tier_weights(&table, false);
+            let total: f64 = tiers.clone().map(|(_, weight)| weight).sum();
+            let rare: f64 = tiers
+                .filter(|(tier, _)| !tier.pull_type.is_common())
+                .map(|(_, weight)| weight)
+                .sum();
+            rare / total
+        };
+        let base = rare_chance(&pull_list);
+        for (streak, ramp) in [(70, 0.0), (75, 0.25), (80, 0.5), (85, 0.75)] {
+            pull_list.dry_streak = streak;
+            let expected = base + (1_f64 - base) * ramp;
+            assert!(
+                (rare_chance(&pull_list) - expected).abs() < 1e-12,
+                "streak {streak}"
+            );
+        }
+    }
+
+    /// `common_list(10)` with one rare item and a fresh history.
+    fn list_with_rare() -> PullList {
+        let mut pull_list = common_list(10);
+        pull_list.insert(Pull::new("Rare".to_owned(), PullType::rare(), 1_f64));
+        pull_list
+    }
+
+    #[test]
+    fn first_pull_is_rare_when_guaranteed() {
+        for seed in 0..200 {
+            let mut pull_list = list_with_rare();
+            pull_list.guarantee_first_rare = true;
+            let pull = pull_list.pull(&mut StdRng::seed_from_u64(seed)).unwrap();
+            assert_eq!(pull.name, "Rare", "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn first_pull_is_a_normal_pull_when_not_guaranteed() {
+        let rares = (0..200)
+            .filter(|&seed| {
+                let mut pull_list = list_with_rare();
+                pull_list.guarantee_first_rare = false;
+                let pull = pull_list.pull(&mut StdRng::seed_from_u64(seed)).unwrap();
+                !pull.pull_type.is_common()
+            })
+            .count();
+        assert!(rares < 100, "{rares} of 200 first pulls were rare");
+    }
+
+    #[test]
+    fn insertion_order_doesnt_change_pulls() {
+        let items: Vec<Pull> = list_with_rare().list;
+        let mut forward = PullList::new();
+        let mut backward = PullList::new();
+        for pull in &items {
+            forward.insert(pull.clone());
+        }
+        for pull in items.iter().rev() {
+            backward.insert(pull.clone());
+        }
+        let mut forward_rng = StdRng::seed_from_u64(7);
+        let mut backward_rng = StdRng::seed_from_u64(7);
+        for _ in 0..500 {
+            let forward_pull = forward.pull(&mut forward_rng).unwrap().name.clone();
+            let backward_pull = backward.pull(&mut backward_rng).unwrap().name.clone();
+            assert_eq!(forward_pull, backward_pull);
+        }
+    }
+
+    #[test]
+    fn failed_import_keeps_the_list() {
+        let mut pull_list = common_list(3);
+        let bad = "New,Common,1\nBroken,Common,nope\n";
+        assert!(pull_list.import_csv(bad.as_bytes(), true).is_err());
+        assert_eq!(pull_list.list.len(), 3);
+
+        let good = "New,Common,1\nItem 0,Common,2\n";
+        assert_eq!(pull_list.import_csv(good.as_bytes(), true).unwrap(), 2);
+        let names: Vec<&str> = pull_list
+            .list
+            .iter()
+            .map(|pull| pull.name.as_str())
+            .collect();
+        assert_eq!(names, ["New", "Item 0"]);
+    }
+
+    #[test]
+    fn pull_many_stops_when_nothing_can_be_pulled() {
+        let mut pull_list = common_list(3);
+        for pull in &mut pull_list.list {
+            pull.enabled = false;
+        }
+        pull_list.invalidate_tables();
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(pull_list.pull_many(usize::MAX, false, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn undo_refunds_the_cost_paid() {
+        let mut pull_list = common_list(3);
+        pull_list.cost = 5;
+        pull_list.balance = 7;
+        pull_list.pull(&mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(pull_list.balance, 2);
+        pull_list.cost = 1;
+        pull_list.undo().unwrap();
+        assert_eq!(pull_list.balance, 7);
+    }
+
+    #[test]
+    fn clearing_history_ends_soft_pity() {
+        let mut pull_list = common_list(3);
+        pull_list.pity_threshold = 90;
+        pull_list.set_soft_pity(Some(70)).unwrap();
+        pull_list.pity_counter = 80;
+        pull_list.dry_streak = 80;
+        assert!(pull_list.soft_pity_ramp(pull_list.dry_streak) > 0_f64);
+
+        pull_list.clear_history();
+        assert_eq!(pull_list.pity_counter, 0);
+        assert_eq!(pull_list.dry_streak, 0);
+        assert_eq!(pull_list.soft_pity_ramp(pull_list.dry_streak), 0_f64);
+    }
+
+    #[test]
+    fn rare_only_draw_keeps_undrawn_commons() {
+        let mut pull_list = list_with_rare();
+        pull_list.set_pull_mode(PullMode::NoReplacement);
+        let mut rng = StdRng::seed_from_u64(0);
+        pull_list.pull(&mut rng).unwrap();
+        pull_list.pull(&mut rng).unwrap();
+        let drawn_commons = pull_list.drawn.len() - 1;
+
+        let pull = pull_list.pull_rare(&mut rng).unwrap();
+        assert_eq!(pull.name, "Rare");
+        assert_eq!(pull_list.drawn.len(), drawn_commons + 1);
+        assert_eq!(pull_list.pool_resets, 0);
+    }
 }
@@ -1,93 +1,1307 @@
-mod maigacha;
-use crate::maigacha::{Pull, PullList, PullType};
+use maigacha::banner::{Banners, FileFormat, DEFAULT_BANNER};
+use maigacha::color;
+use maigacha::maigacha::{
+    Chance, ConflictPolicy, ListOptions, PullMode, Severity, SortBy, DEFAULT_PRECISION,
+    MAX_BALANCE, MAX_COOLDOWN_SECS,
+};
+use maigacha::{Pull, PullList, PullType};
 
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 const MAIGACHA_FILE: &str = "maigacha.json";
+/// Environment variable naming the file when `--file` isn't given.
+const FILE_ENV: &str = "MAIGACHA_FILE";
+/// `--file` and `--output` value for stdin and stdout.
+const STDIN_FILE: &str = "-";
+/// Config subdirectory older versions misspelled.
+const LEGACY_DIR: &str = "maigach";
+const DEFAULT_PER_PAGE: usize = 20;
+/// Terminal width assumed when `COLUMNS` isn't set.
+const DEFAULT_COLUMNS: usize = 80;
 const RESET: &str = "\x1b[0m";
 
 const GREEN: &str = "\x1b[32m";
 const YELLOW: &str = "\x1b[33m";
 
-fn main() -> Result<()> {
-    let args = Cli::from_args();
-    let path = if let Some(path) = args.file {
-        path
-    } else {
-        get_default_file()?
+/// Exit status of a run, listed under "Exit codes" in the README.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success = 0,
+    /// An item, tier or banner wasn't found.
+    NotFound = 1,
+    /// There was nothing to pull.
+    NothingToPull = 2,
+    /// A file couldn't be read, written or parsed.
+    Io = 3,
+    /// The arguments were invalid, or the list failed validation.
+    Invalid = 4,
+}
+
+fn main() -> ExitCode {
+    let status = run().unwrap_or_else(|err| {
+        eprintln!("Error: {err}");
+        Status::Io
+    });
+    ExitCode::from(status as u8)
+}
+
+fn run() -> Result<Status> {
+    let args = match Cli::from_args_safe() {
+        Ok(args) => args,
+        Err(err) if !err.use_stderr() => err.exit(),
+        Err(err) => {
+            eprintln!("{}", err.message);
+            return Ok(Status::Invalid);
+        }
+    };
+    let json = args.json;
+    init_color(args.no_color || json);
+    let path = resolve_file(args.file)?;
+    if let Command::Reset { yes } = args.command {
+        let save_path = args.output.unwrap_or(path);
+        return reset(
+            &save_path,
+            yes,
+            args.file_format,
+            !args.no_backup,
+            args.pretty,
+        );
+    }
+    let mut banners = get_banners(&path, args.file_format, args.force_new)?;
+    let path = args.output.unwrap_or(path);
+    let mut rng: Box<dyn RngCore> = match args.seed {
+        Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+        None => Box::new(rand::thread_rng()),
+    };
+    if let Command::Repl = args.command {
+        if path == Path::new(STDIN_FILE) {
+            println!("The REPL reads commands from stdin, so it can't also read the file from it.");
+            return Ok(Status::Invalid);
+        }
+        if repl(&mut banners, &args.banner, json, &mut *rng)? || banners.migrated {
+            save(
+                &banners,
+                &path,
+                args.file_format,
+                !args.no_backup,
+                args.pretty,
+            )?;
+        }
+        return Ok(Status::Success);
+    }
+    if let Command::Watch { interval } = args.command {
+        if path == Path::new(STDIN_FILE) {
+            println!("Watch saves after every pull, so it can't read the file from stdin.");
+            return Ok(Status::Invalid);
+        }
+        if interval == 0 {
+            println!("interval can't be 0.");
+            return Ok(Status::Invalid);
+        }
+        return watch(
+            &mut banners,
+            &args.banner,
+            Duration::from_secs(interval),
+            json,
+            &mut *rng,
+            |banners| {
+                save(
+                    banners,
+                    &path,
+                    args.file_format,
+                    !args.no_backup,
+                    args.pretty,
+                )
+            },
+        );
+    }
+    let (status, changed) = run_command(&mut banners, &args.banner, args.command, json, &mut *rng)?;
+    // A migrated file is saved so it isn't migrated again, but isn't printed
+    // after a command that only reads.
+    if changed || (banners.migrated && path != Path::new(STDIN_FILE)) {
+        save(
+            &banners,
+            &path,
+            args.file_format,
+            !args.no_backup,
+            args.pretty,
+        )?;
+    }
+    Ok(status)
+}
+
+/// Runs a command against `banners`, returning its status and whether it changed
+/// anything that needs saving.
+fn run_command(
+    banners: &mut Banners,
+    banner: &str,
+    command: Command,
+    json: bool,
+    rng: &mut dyn RngCore,
+) -> Result<(Status, bool)> {
+    let command = match command {
+        Command::Banner(command) => return Ok(run_banner_command(banners, command)),
+        command => command,
     };
-    let mut pull_list = get_maigacha_list(&path);
-    match args.command {
+    let Some(pull_list) = banners.get_mut(banner) else {
+        eprintln!(
+            r#"Banner "{}" doesn't exist, create it with banner new."#,
+            banner
+        );
+        return Ok((Status::NotFound, false));
+    };
+    // On stderr, so it can't end up in --json output.
+    if matches!(
+        command,
+        Command::Pull { .. }
+            | Command::PullTen { .. }
+            | Command::PullStepUp { .. }
+            | Command::Simulate { .. }
+    ) {
+        if let Some(issue) = pull_list.low_rare_chance() {
+            eprintln!("{issue}");
+        }
+    }
+    let mut status = Status::Success;
+    match command {
         Command::Add {
             name,
             pull_type,
             chance,
+            force,
+            tags,
+            note,
+        } => {
+            let mut pull = Pull::with_chance(name, pull_type, chance);
+            for tag in &tags {
+                pull.add_tag(tag);
+            }
+            if let Some(note) = note {
+                pull.set_note(note);
+            }
+            if !pull_list.has_tier(&pull.pull_type) {
+                println!("Unknown tier \"{}\".", pull.pull_type);
+                status = Status::NotFound;
+            } else if force {
+                pull_list.replace(pull);
+            } else if !pull_list.insert(pull) {
+                println!("Item already exists, use edit instead.");
+                status = Status::Invalid;
+            }
+        }
+        Command::AddMany {
+            prefix,
+            pull_type,
+            chance,
+            count,
+        } => match pull_list.add_many(&prefix, &pull_type, chance, count) {
+            Ok(added) => println!("Added {added}, skipped {}.", count - added),
+            Err(err) => {
+                println!("{err}.");
+                status = if pull_list.has_tier(&pull_type) {
+                    Status::Invalid
+                } else {
+                    Status::NotFound
+                };
+            }
+        },
+        Command::AddFile { path } => {
+            let (added, skipped) = pull_list.add_lines(&std::fs::read_to_string(&path)?);
+            for err in &skipped {
+                println!("{err}");
+            }
+            println!("Added {added}, skipped {}.", skipped.len());
+        }
+        Command::Edit {
+            name,
+            chance,
+            pull_type,
+            note,
+            ignore_case,
+        } => match resolve_name(pull_list, &name, ignore_case).and_then(|name| {
+            pull_list
+                .edit(&name, chance, pull_type, note)
+                .map(|()| name)
+        }) {
+            Ok(name) => println!(r#""{name}", has been edited."#),
+            Err(err) => {
+                println!(r#""{name}", {err}."#);
+                status = not_found_or_invalid(pull_list, &name);
+            }
+        },
+        Command::Favorite { name } => match pull_list.toggle_favorite(&name) {
+            Ok(true) => println!(r#""{name}" is now a favorite."#),
+            Ok(false) => println!(r#""{name}" is no longer a favorite."#),
+            Err(err) => {
+                println!(r#""{name}", {err}."#);
+                status = Status::NotFound;
+            }
+        },
+        Command::Enable { name } => status = set_enabled(pull_list, &name, true),
+        Command::Disable { name } => status = set_enabled(pull_list, &name, false),
+        Command::Rename {
+            old,
+            new,
+            ignore_case,
+        } => match resolve_name(pull_list, &old, ignore_case)
+            .and_then(|old| pull_list.rename(&old, &new).map(|()| old))
+        {
+            Ok(old) => println!(r#""{old}", has been renamed to "{new}"."#),
+            Err(err) => {
+                println!(r#""{old}", {err}."#);
+                status = not_found_or_invalid(pull_list, &old);
+            }
+        },
+        Command::Copy { src, dest, chance } => match pull_list.copy(&src, &dest, chance) {
+            Ok(()) => println!(r#""{src}", has been copied to "{dest}"."#),
+            Err(err) => {
+                println!(r#""{src}", {err}."#);
+                status = not_found_or_invalid(pull_list, &src);
+            }
+        },
+        Command::Remove {
+            names,
+            index: None,
+            ignore_case,
+        } => {
+            let mut removed = 0;
+            for name in &names {
+                match pull_list.find_index(name, ignore_case) {
+                    Ok(index) => {
+                        let pull = pull_list.remove_at(index).unwrap();
+                        removed += 1;
+                        println!(r#""{}", has been removed."#, pull.name);
+                    }
+                    Err(err) => {
+                        println!(r#""{name}", {err}."#);
+                        status = Status::NotFound;
+                    }
+                }
+            }
+            if names.len() > 1 {
+                println!("Removed {removed}, not found {}.", names.len() - removed);
+            }
+        }
+        Command::Remove {
+            index: Some(index), ..
+        } => match pull_list.remove_at(index) {
+            Some(pull) => println!(r#""{}", has been removed."#, pull.name),
+            None => {
+                println!(
+                    "Index {index} is out of range, the list has {} items.",
+                    pull_list.list().len()
+                );
+                status = Status::NotFound;
+            }
+        },
+        Command::Pull {
+            count,
+            confirm,
+            rare_only,
+            dry_run,
+            ignore_cooldown,
+            verbose,
+            animate,
+        } => {
+            if rare_only && !pull_list.has_rare() {
+                println!("No rare items to pull.");
+                return Ok((Status::NothingToPull, false));
+            }
+            if !dry_run && !ignore_cooldown && on_cooldown(pull_list) {
+                return Ok((Status::Invalid, false));
+            }
+            if !dry_run && !can_afford(pull_list, count.unwrap_or(1)) {
+                return Ok((Status::Invalid, false));
+            }
+            if dry_run {
+                if !json {
+                    println!("(dry run)");
+                }
+                let pulls: Vec<Pull> = (0..count.unwrap_or(1))
+                    .map_while(|_| pull_list.peek_pull(rare_only, rng).cloned())
+                    .collect();
+                return Ok((
+                    show_pulls(pull_list, &pulls, count.is_some(), verbose, json)?,
+                    false,
+                ));
+            }
+            if confirm && !self::confirm("Pull now?")? {
+                return Ok((status, false));
+            }
+            let pulls = match count {
+                None => {
+                    let pull = if rare_only {
+                        pull_list.pull_rare(rng)
+                    } else {
+                        pull_list.pull(rng)
+                    };
+                    pull.cloned().into_iter().collect()
+                }
+                Some(count) => pull_list.pull_many(count, rare_only, rng),
+            };
+            if animate {
+                animate_roll()?;
+            }
+            status = show_pulls(pull_list, &pulls, count.is_some(), verbose, json)?;
+        }
+        Command::PullTen {
+            confirm,
+            ignore_cooldown,
+            verbose,
+        } => {
+            if !ignore_cooldown && on_cooldown(pull_list) {
+                return Ok((Status::Invalid, false));
+            }
+            if !can_afford(pull_list, 10) {
+                return Ok((Status::Invalid, false));
+            }
+            if confirm && !self::confirm("Pull now?")? {
+                return Ok((status, false));
+            }
+            let pulls = pull_list.pull_ten(rng);
+            status = show_pulls(pull_list, &pulls, true, verbose, json)?;
+        }
+        Command::PullStepUp {
+            count,
+            ignore_cooldown,
+            verbose,
+        } => {
+            if !ignore_cooldown && on_cooldown(pull_list) {
+                return Ok((Status::Invalid, false));
+            }
+            if !can_afford(pull_list, count) {
+                return Ok((Status::Invalid, false));
+            }
+            let pulls = pull_list.pull_step_up(count, rng);
+            status = show_pulls(pull_list, &pulls, true, verbose, json)?;
+            if !json && !pulls.is_empty() {
+                let mut distinct: Vec<&str> = Vec::new();
+                for pull in &pulls {
+                    if !distinct.contains(&pull.name.as_str()) {
+                        distinct.push(&pull.name);
+                    }
+                }
+                println!("Collected {} distinct items.", distinct.len());
+                if count > pull_list.list().len() {
+                    println!(
+                        "There are only {} items, so every item was collected before repeating.",
+                        pull_list.list().len()
+                    );
+                }
+            }
+        }
+        Command::List { .. } if json => {
+            print_json(pull_list)?;
+            return Ok((status, false));
+        }
+        Command::List {
+            sort,
+            desc,
+            page,
+            per_page,
+            flat,
+            tag,
+            pull_type,
+            precision,
+            notes,
         } => {
-            if chance > 0_f64 {
-                pull_list.insert(Pull::new(name, pull_type, chance));
+            if per_page == Some(0) {
+                println!("per-page can't be 0.");
+                return Ok((Status::Invalid, false));
+            }
+            pull_list.print_list(&ListOptions {
+                sort,
+                desc,
+                page: page.or(per_page.map(|_| 1)),
+                per_page: per_page.unwrap_or(DEFAULT_PER_PAGE),
+                flat,
+                tag,
+                pull_type,
+                precision: precision.unwrap_or(DEFAULT_PRECISION),
+                notes,
+            });
+            return Ok((status, false));
+        }
+        Command::Tree => {
+            if pull_list.list().is_empty() {
+                println!("List is empty.");
             } else {
-                println!("chance can't be 0 or less.");
+                pull_list.print_tree();
             }
+            return Ok((status, false));
         }
-        Command::Remove { name } => {
-            if pull_list.remove(&name).is_some() {
-                println!(r#""{name}", has been removed."#);
+        Command::Find { query } => {
+            let pulls = pull_list.find(&query);
+            if pulls.is_empty() {
+                println!(r#"No items matching "{query}"."#);
+                status = Status::NotFound;
             } else {
-                println!(r#""{name}", not in list."#);
+                pull_list.print_pulls(&pulls);
             }
+            return Ok((status, false));
         }
-        Command::Pull => pull_list.pull().map_or_else(
+        Command::History {
+            pull_type,
+            since,
+            until,
+            name,
+        } if json => {
+            print_json(&pull_list.pull_history.filter(
+                pull_type.as_ref(),
+                since,
+                until,
+                name.as_deref(),
+            ))?;
+            return Ok((status, false));
+        }
+        Command::History {
+            pull_type: None,
+            since: None,
+            until: None,
+            name: None,
+        } => {
+            pull_list.pull_history.print();
+            return Ok((status, false));
+        }
+        Command::History {
+            pull_type,
+            since,
+            until,
+            name,
+        } => {
+            pull_list.pull_history.print_filtered(
+                pull_type.as_ref(),
+                since,
+                until,
+                name.as_deref(),
+            );
+            return Ok((status, false));
+        }
+        Command::AddTier {
+            name,
+            rarity,
+            after,
+        } => match pull_list.add_tier(name.clone(), rarity, after.as_ref()) {
+            Ok(()) => println!("Tier \"{name}\" added."),
+            Err(err) => {
+                println!("{err}.");
+                status = Status::Invalid;
+            }
+        },
+        Command::SetColor { tier, color } => match pull_list.set_tier_color(&tier, color) {
+            Ok(()) => println!(r#"Color of "{tier}" updated."#),
+            Err(err) => {
+                println!("{err}.");
+                status = Status::Invalid;
+            }
+        },
+        Command::Tiers => {
+            let chances = pull_list.tier_chances();
+            for tier in &pull_list.tiers {
+                let chance = chances
+                    .iter()
+                    .find(|(pull_type, _)| *pull_type == tier.name)
+                    .map_or(0_f64, |(_, chance)| *chance);
+                if pull_list.tier_weights.is_empty() {
+                    println!(
+                        "{} : 1 in {} ({:.2}%)",
+                        tier.name,
+                        tier.rarity,
+                        chance * 100_f64
+                    );
+                } else {
+                    let weight = pull_list.tier_weights.get(&tier.name).unwrap_or(&0_f64);
+                    println!("{} : weight {weight} ({:.2}%)", tier.name, chance * 100_f64);
+                }
+            }
+            return Ok((status, false));
+        }
+        Command::SetTierWeight { tier, weight } => match pull_list.set_tier_weight(&tier, weight) {
+            Ok(()) => match weight {
+                Some(weight) => println!("{tier} weight set to {weight}."),
+                None => println!("{tier} weight removed."),
+            },
+            Err(err) => {
+                println!(r#""{tier}", {err}."#);
+                status = Status::Invalid;
+            }
+        },
+        Command::RateUp { clear: true, .. } => {
+            pull_list.clear_rate_up();
+            println!("Rate-up cleared.");
+        }
+        Command::RateUp { name, factor, .. } => {
+            let name = name.unwrap_or_default();
+            match pull_list.set_rate_up(&name, factor) {
+                Ok(()) => println!(r#""{name}" is rate-up x{}."#, pull_list.rate_up_factor),
+                Err(err) => {
+                    println!(r#""{name}", {err}."#);
+                    status = not_found_or_invalid(pull_list, &name);
+                }
+            }
+        }
+        Command::Report {
+            format: ReportFormat::Markdown,
+        } => {
+            print!("{}", pull_list.report_markdown());
+            return Ok((status, false));
+        }
+        Command::Export {
+            format: Format::Csv,
+            path,
+        } => {
+            pull_list.export_csv(File::create(&path)?)?;
+            println!(
+                "Exported {} items to {}.",
+                pull_list.list().len(),
+                path.display()
+            );
+            return Ok((status, false));
+        }
+        Command::ExportHistory { path } => {
+            pull_list.pull_history.export_csv(File::create(&path)?)?;
+            println!(
+                "Exported {} history entries to {}.",
+                pull_list.pull_history.history.len(),
+                path.display()
+            );
+            return Ok((status, false));
+        }
+        Command::Import {
+            format: Format::Csv,
+            path,
+            replace,
+        } => {
+            let file = File::open(&path)?;
+            let count = pull_list.import_csv(file, replace)?;
+            println!("Imported {count} items from {}.", path.display());
+        }
+        Command::Merge {
+            path,
+            on_conflict,
+            from,
+            history,
+        } => {
+            let mut other = Banners::load_from_file(&path, FileFormat::from_path(&path))?;
+            let Some(other) = other.banners.remove(&from) else {
+                println!(r#"Banner "{from}" doesn't exist in {}."#, path.display());
+                return Ok((Status::NotFound, false));
+            };
+            let summary = pull_list.merge(other, on_conflict, history);
+            println!(
+                "Added {}, skipped {}, overwritten {}, renamed {}.",
+                summary.added, summary.skipped, summary.overwritten, summary.renamed
+            );
+            if summary.tiers_added > 0 {
+                println!("Added {} tiers.", summary.tiers_added);
+            }
+        }
+        Command::Undo { id: None } => pull_list.undo().map_or_else(
             || {
-                println!("Nothing to pull.");
+                println!("Nothing to undo.");
             },
-            |pull| {
-                let color = match pull.pull_type {
-                    PullType::Common => GREEN,
-                    PullType::Rare => YELLOW,
-                };
+            |entry| {
+                println!("Undid {}", pull_list.pull_history.format_entry(&entry));
+            },
+        ),
+        Command::Undo { id: Some(id) } => match pull_list.undo_id(id) {
+            Some(entry) => println!("Undid {}", pull_list.pull_history.format_entry(&entry)),
+            None => {
+                println!("No pull #{id} in history.");
+                status = Status::NotFound;
+            }
+        },
+        Command::Simulate { n, histogram } => {
+            print_simulation(pull_list, n, histogram, rng);
+            return Ok((status, false));
+        }
+        Command::Expected => {
+            let expected = pull_list.expected_pulls_until_rare();
+            if expected.is_infinite() {
+                println!("No rare items.");
+            } else {
+                println!("Expected pulls until a rare: {expected:.2}");
+            }
+            return Ok((status, false));
+        }
+        Command::Odds { name } => {
+            match pull_list.item_odds(&name) {
+                None => {
+                    println!(r#""{name}", not in list."#);
+                    status = Status::NotFound;
+                }
+                Some(odds) if odds > 0_f64 => println!(
+                    r#""{name}" : {:.2}% (1 in {:.1})"#,
+                    odds * 100_f64,
+                    1_f64 / odds
+                ),
+                Some(_) => println!(r#""{name}" : 0.00% (never)"#),
+            }
+            return Ok((status, false));
+        }
+        Command::Probabilities => {
+            if pull_list.list().is_empty() {
+                println!("List is empty.");
+            } else {
+                print_probabilities(pull_list);
+            }
+            return Ok((status, false));
+        }
+        Command::Validate => {
+            let issues = pull_list.validate();
+            for issue in &issues {
+                println!("{issue}");
+            }
+            if issues.is_empty() {
+                println!("No problems found.");
+            }
+            if issues.iter().any(|issue| issue.severity == Severity::Error) {
+                status = Status::Invalid;
+            }
+            return Ok((status, false));
+        }
+        Command::Inventory => {
+            let mut counts: Vec<(String, usize)> =
+                pull_list.pull_history.counts().into_iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            if json {
+                print_json(&counts)?;
+            } else if counts.is_empty() {
+                println!("History is empty.");
+            } else {
+                let max_length = counts
+                    .iter()
+                    .map(|(name, _)| name.chars().count())
+                    .max()
+                    .unwrap()
+                    + 2;
+                for (name, count) in &counts {
+                    println!("{:<max_length$} : {count}", format!("\"{name}\""));
+                }
                 println!(
-                    "Pulled a {color}{:#?}{RESET}\n{:#?} : {:#?}",
-                    pull.pull_type, pull.name, pull.chance
+                    "Counts cover the {} pulls kept in history.",
+                    pull_list.pull_history.history.len()
                 );
+            }
+            return Ok((status, false));
+        }
+        Command::Stats => {
+            if json {
+                print_json(&serde_json::json!({
+                    "total_pulls": pull_list.total_pulls,
+                    "history": pull_list.pull_history.stats(),
+                }))?;
+            } else {
+                print_stats(pull_list);
+            }
+            return Ok((status, false));
+        }
+        Command::ClearHistory { yes } => {
+            if !yes && !confirm("Clear the history?")? {
+                println!("Cancelled.");
+                return Ok((status, false));
+            }
+            pull_list.clear_history();
+            println!("History cleared.");
+        }
+        Command::Scale { factor, pull_type } => match pull_list.scale(factor, pull_type.as_ref()) {
+            Ok(()) => match pull_type {
+                Some(pull_type) => println!("{pull_type} chances scaled by {factor}."),
+                None => println!("Chances scaled by {factor}."),
             },
-        ),
-        Command::List => {
-            pull_list.print_list();
+            Err(err) => {
+                println!("{err}.");
+                status = Status::Invalid;
+            }
+        },
+        Command::Round { places } => match pull_list.round(places) {
+            Ok(changed) => println!("Rounded {changed} chances to {places} decimal places."),
+            Err(err) => {
+                println!("{err}.");
+                status = Status::Invalid;
+            }
+        },
+        Command::Normalize { yes } => {
+            if !yes && !confirm("Rescale every chance so each tier sums to 100?")? {
+                println!("Cancelled.");
+                return Ok((status, false));
+            }
+            pull_list.normalize();
+            println!("Chances normalized.");
         }
-        Command::History => {
-            pull_list.pull_history.print();
+        Command::SetHistorySize { size } => {
+            if size > 0 {
+                pull_list.pull_history.resize(size);
+                println!("History size set to {size}.");
+            } else {
+                println!("size can't be 0.");
+                status = Status::Invalid;
+            }
+        }
+        Command::Banner(_) | Command::Reset { .. } | Command::Repl | Command::Watch { .. } => {
+            unreachable!()
+        }
+        Command::SetRarity { rarity } => {
+            if rarity > 0 {
+                let old = std::mem::replace(&mut pull_list.rare_rarity, rarity);
+                pull_list.invalidate_tables();
+                println!("Rare rarity changed from 1 in {old} to 1 in {rarity}.");
+            } else {
+                println!("rarity can't be 0.");
+                status = Status::Invalid;
+            }
+        }
+        Command::SetTimezone { utc } => {
+            pull_list.pull_history.utc = utc;
+            println!(
+                "History timestamps are now in {}.",
+                if utc { "UTC" } else { "local time" }
+            );
+        }
+        Command::SetTimeFormat { format } => {
+            match pull_list.pull_history.set_timestamp_format(format.clone()) {
+                Ok(()) => match format {
+                    Some(format) => println!("History timestamps now use {format:?}."),
+                    None => println!("History timestamps now use the default format."),
+                },
+                Err(err) => {
+                    println!("{err}.");
+                    status = Status::Invalid;
+                }
+            }
+        }
+        Command::SetCooldown { secs } => {
+            if secs.is_some_and(|secs| secs > MAX_COOLDOWN_SECS) {
+                println!("cooldown can't be over {MAX_COOLDOWN_SECS} seconds.");
+                return Ok((Status::Invalid, false));
+            }
+            pull_list.cooldown_secs = secs.filter(|&secs| secs > 0);
+            match pull_list.cooldown_secs {
+                Some(secs) => println!("Cooldown set to {secs} seconds."),
+                None => println!("Cooldown removed."),
+            }
+        }
+        Command::AddBalance { amount } => match pull_list.balance.checked_add(amount) {
+            Some(balance) if balance <= MAX_BALANCE => {
+                pull_list.balance = balance;
+                println!("Balance is now {balance}.");
+            }
+            _ => {
+                println!("balance can't be over {MAX_BALANCE}.");
+                status = Status::Invalid;
+            }
+        },
+        Command::SetCost { cost } => {
+            if cost > MAX_BALANCE {
+                println!("cost can't be over {MAX_BALANCE}.");
+                return Ok((Status::Invalid, false));
+            }
+            pull_list.cost = cost;
+            println!("Pulls now cost {cost}.");
+        }
+        Command::SetSoftPity { start } => match pull_list.set_soft_pity(start) {
+            Ok(()) => match start {
+                Some(start) => println!(
+                    "Rare chance now ramps up after {start} dry pulls until {}.",
+                    pull_list.pity_threshold
+                ),
+                None => println!("Soft pity turned off."),
+            },
+            Err(err) => {
+                println!("{err}.");
+                status = Status::Invalid;
+            }
+        },
+        Command::SetPullMode { mode } => {
+            pull_list.set_pull_mode(mode.unwrap_or_default());
+            match pull_list.pull_mode {
+                PullMode::Replacement => println!("Pulled items now go back into the pool."),
+                PullMode::NoReplacement => {
+                    println!("Pulled items now stay out of the pool until it's empty.")
+                }
+            }
+        }
+        Command::SetFirstRare { guarantee } => {
+            pull_list.guarantee_first_rare = guarantee.unwrap_or(true);
+            if pull_list.guarantee_first_rare {
+                println!("A rare is now forced when none is left in the history.");
+            } else {
+                println!("Rares are no longer forced by the history, only by pity.");
+            }
+        }
+        Command::SetRareMessage { message } => {
+            match &message {
+                Some(message) => println!("Rare pulls now announce: {message}"),
+                None => println!("Rare pulls are no longer announced."),
+            }
+            pull_list.rare_message = message;
+        }
+        Command::SetPity { threshold } => {
+            if threshold > 0 {
+                pull_list.pity_threshold = threshold;
+                println!("Pity threshold set to {threshold}.");
+            } else {
+                println!("threshold can't be 0.");
+                status = Status::Invalid;
+            }
         }
     }
-    pull_list.save_to_json(path.to_str().unwrap())?;
-    Ok(())
+    Ok((status, true))
 }
 
 #[derive(Debug, StructOpt)]
 enum Command {
     /// Add an item to the list.
     ///
-    /// Add format is <name> <common/rare> <chance>
+    /// Add format is <name> <tier> <chance>
     #[structopt(alias = "a")]
     Add {
         name: String,
         pull_type: PullType,
-        chance: f64,
+        /// A weight, or a percentage like 25%.
+        chance: Chance,
+        /// Overwrite an existing item with the same name.
+        #[structopt(long = "force")]
+        force: bool,
+        /// Tag the item, can be repeated.
+        #[structopt(long = "tag", number_of_values = 1)]
+        tags: Vec<String>,
+        /// A reminder about the item, shown by list --notes.
+        #[structopt(long = "note")]
+        note: Option<String>,
+    },
+    /// Adds <count> items named <prefix>_1, <prefix>_2, etc. with the same tier and chance.
+    AddMany {
+        prefix: String,
+        pull_type: PullType,
+        chance: Chance,
+        count: usize,
+    },
+    /// Add items from a file with a <name>:<tier>:<chance> line per item.
+    ///
+    /// Blank lines and lines starting with # are ignored.
+    AddFile {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Edit an item in place.
+    ///
+    /// Only the given fields are changed.
+    #[structopt(alias = "e")]
+    Edit {
+        name: String,
+        #[structopt(long = "chance")]
+        chance: Option<Chance>,
+        #[structopt(long = "type")]
+        pull_type: Option<PullType>,
+        /// Replace the note, an empty note removes it.
+        #[structopt(long = "note")]
+        note: Option<String>,
+        /// Match the name ignoring case.
+        #[structopt(long = "ignore-case")]
+        ignore_case: bool,
     },
-    /// Remove an item from the list.
+    /// Marks an item as a favorite, or unmarks it if it already is.
+    ///
+    /// Favorites are starred in the list and celebrated when pulled.
+    Favorite { name: String },
+    /// Lets a disabled item be pulled again, with its chance unchanged.
+    Enable { name: String },
+    /// Keeps an item in the list but stops it from being pulled.
+    Disable { name: String },
+    /// Rename an item, keeping its history.
+    Rename {
+        old: String,
+        new: String,
+        /// Match the old name ignoring case.
+        #[structopt(long = "ignore-case")]
+        ignore_case: bool,
+    },
+    /// Copy an item's tier, chance and tags to a new item.
+    Copy {
+        src: String,
+        dest: String,
+        /// Chance of the copy instead of the original's.
+        #[structopt(long = "chance")]
+        chance: Option<Chance>,
+    },
+    /// Remove items from the list.
     #[structopt(alias = "r")]
-    Remove { name: String },
+    Remove {
+        #[structopt(required_unless = "index")]
+        names: Vec<String>,
+        /// Remove the item at this 0-based position in the list instead.
+        #[structopt(long = "index", conflicts_with = "names")]
+        index: Option<usize>,
+        /// Match the names ignoring case.
+        #[structopt(long = "ignore-case")]
+        ignore_case: bool,
+    },
     /// Pulls an item from the list.
+    ///
+    /// Pulls <count> items in one go when given.
     #[structopt(alias = "p")]
-    Pull,
+    Pull {
+        count: Option<usize>,
+        /// Ask before pulling.
+        #[structopt(long = "confirm")]
+        confirm: bool,
+        /// Only pull rare items, still weighted by tier and chance.
+        #[structopt(long = "rare-only")]
+        rare_only: bool,
+        /// Show what would be pulled without recording or saving anything.
+        ///
+        /// Each pull is picked from the current state, so pity doesn't build up over <count>.
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+        /// Pull even if the cooldown hasn't passed.
+        #[structopt(long = "ignore-cooldown")]
+        ignore_cooldown: bool,
+        /// Show each pull in order instead of grouping them by item.
+        #[structopt(long = "verbose")]
+        verbose: bool,
+        /// Show a short rolling animation before the result, when output is a terminal.
+        #[structopt(long = "animate")]
+        animate: bool,
+    },
+    /// Pulls <count> items without repeating one until every item was pulled.
+    #[structopt(name = "pull-stepup")]
+    PullStepUp {
+        count: usize,
+        /// Pull even if the cooldown hasn't passed.
+        #[structopt(long = "ignore-cooldown")]
+        ignore_cooldown: bool,
+        /// Show each pull in order instead of grouping them by item.
+        #[structopt(long = "verbose")]
+        verbose: bool,
+    },
+    /// Pulls an item every <interval> seconds until Ctrl-C, saving after each pull.
+    ///
+    /// Waits out the cooldown when one is set, and stops when nothing can be pulled.
+    Watch {
+        #[structopt(long = "interval", default_value = "60")]
+        interval: u64,
+    },
+    /// Pulls ten items, the tenth is rare if none of the first nine were.
+    PullTen {
+        /// Ask before pulling.
+        #[structopt(long = "confirm")]
+        confirm: bool,
+        /// Pull even if the cooldown hasn't passed.
+        #[structopt(long = "ignore-cooldown")]
+        ignore_cooldown: bool,
+        /// Show each pull in order instead of grouping them by item.
+        #[structopt(long = "verbose")]
+        verbose: bool,
+    },
     /// Shows the list.
     #[structopt(alias = "l")]
-    List,
+    List {
+        /// Sort each tier by name or chance.
+        #[structopt(long = "sort")]
+        sort: Option<SortBy>,
+        /// Sort in descending order.
+        #[structopt(long = "desc")]
+        desc: bool,
+        /// Show only this page of items, starting at 1.
+        #[structopt(long = "page")]
+        page: Option<usize>,
+        /// Items per page, defaults to 20.
+        #[structopt(long = "per-page")]
+        per_page: Option<usize>,
+        /// Show the items as one list instead of grouped by tier.
+        #[structopt(long = "flat")]
+        flat: bool,
+        /// Only show items with this tag.
+        #[structopt(long = "tag")]
+        tag: Option<String>,
+        /// Only show items in this tier.
+        #[structopt(long = "type")]
+        pull_type: Option<PullType>,
+        /// Decimal places of the chances, defaults to 4.
+        #[structopt(long = "precision")]
+        precision: Option<usize>,
+        /// Show each item's note under it.
+        #[structopt(long = "notes")]
+        notes: bool,
+    },
+    /// Shows the items nested by tier and tag.
+    Tree,
+    /// Shows the items whose name contains <query>, ignoring case.
+    #[structopt(alias = "f")]
+    Find { query: String },
+    /// Adds the items of a banner in another file.
+    Merge {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// What to do with items already in the list: skip, overwrite or rename.
+        #[structopt(long = "on-conflict", default_value = "skip")]
+        on_conflict: ConflictPolicy,
+        /// Banner of the other file to merge.
+        #[structopt(long = "from", default_value = DEFAULT_BANNER)]
+        from: String,
+        /// Also merge the other history, ordered by time.
+        #[structopt(long = "history")]
+        history: bool,
+    },
     /// Shows the history.
     #[structopt(alias = "h")]
-    History,
+    History {
+        /// Only show pulls of this tier.
+        #[structopt(long = "type")]
+        pull_type: Option<PullType>,
+        /// Only show pulls from this date on, as YYYY-MM-DD[ HH:MM[:SS]].
+        #[structopt(long = "since", parse(try_from_str = parse_since))]
+        since: Option<DateTime<Local>>,
+        /// Only show pulls up to this date, as YYYY-MM-DD[ HH:MM[:SS]].
+        #[structopt(long = "until", parse(try_from_str = parse_until))]
+        until: Option<DateTime<Local>>,
+        /// Only show pulls whose name contains this, ignoring case.
+        #[structopt(long = "name")]
+        name: Option<String>,
+    },
+    /// Prints the items with their chances and the rarity settings, for sharing.
+    Report {
+        /// Format of the report, currently only markdown.
+        #[structopt(long = "format", default_value = "markdown")]
+        format: ReportFormat,
+    },
+    /// Exports the list to a file.
+    Export {
+        /// Format of the exported file, currently only csv.
+        #[structopt(long = "format", default_value = "csv")]
+        format: Format,
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Exports the history to a CSV file.
+    ExportHistory {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Imports items from a file.
+    ///
+    /// Nothing is imported if any row is invalid.
+    Import {
+        /// Format of the imported file, currently only csv.
+        #[structopt(long = "format", default_value = "csv")]
+        format: Format,
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Clear the list before importing.
+        #[structopt(long = "replace")]
+        replace: bool,
+    },
+    /// Simulates <n> pulls without touching the list or history.
+    Simulate {
+        n: usize,
+        /// Show the hits as bars scaled to the terminal width.
+        #[structopt(long = "histogram")]
+        histogram: bool,
+    },
+    /// Shows the expected number of pulls until a rare.
+    Expected,
+    /// Shows the chance of the next pull giving <name>.
+    ///
+    /// Counts the tier odds, pity and the rare history check.
+    Odds { name: String },
+    /// Shows the chance of the next pull giving each item, and checks they add up to 100%.
+    Probabilities,
+    /// Checks the list for bad chances, duplicate names and empty tiers.
+    ///
+    /// Exits with status 4 if any errors are found.
+    Validate,
+    /// Shows how many times each item was pulled, most pulled first.
+    ///
+    /// Only pulls still in the history are counted.
+    Inventory,
+    /// Shows statistics about the history.
+    #[structopt(alias = "s")]
+    Stats,
+    /// Undoes the last pull.
+    #[structopt(alias = "u")]
+    Undo {
+        /// Undo the pull with this number from the history instead.
+        #[structopt(long = "id")]
+        id: Option<u64>,
+    },
+    /// Clears the history, keeping the list.
+    ClearHistory {
+        /// Skip the confirmation prompt.
+        #[structopt(long = "yes", short = "y")]
+        yes: bool,
+    },
+    /// Multiplies every chance by <factor>.
+    ///
+    /// Odds within a tier stay the same, which helps bring lists with different
+    /// weight scales in line before merging them.
+    Scale {
+        factor: f64,
+        /// Only scale the items in this tier.
+        #[structopt(long = "type")]
+        pull_type: Option<PullType>,
+    },
+    /// Rounds every chance to <places> decimals, from 0 to 10.
+    ///
+    /// Cleans up float drift like 4.999999999 left by scale or normalize.
+    Round { places: u32 },
+    /// Rescales chances so each tier sums to 100, keeping the odds the same.
+    Normalize {
+        /// Skip the confirmation prompt.
+        #[structopt(long = "yes", short = "y")]
+        yes: bool,
+    },
+    /// Reads commands from stdin until exit, quit or the end of input.
+    ///
+    /// The file is loaded once and saved when the REPL ends.
+    Repl,
+    /// Replaces the whole file, every banner included, with an empty default banner.
+    ///
+    /// The old file is first copied to <file>.<timestamp>.bak unless --no-backup is given.
+    Reset {
+        /// Confirm the reset, required since it can't be undone.
+        #[structopt(long = "yes", short = "y")]
+        yes: bool,
+    },
+    /// Sets how many pulls the history keeps.
+    SetHistorySize { size: usize },
+    /// Manages banners.
+    Banner(BannerCommand),
+    /// Sets the 1 in <rarity> chance of a pull leaving the common tier.
+    SetRarity { rarity: usize },
+    /// Sets whether history timestamps are in utc or local time.
+    SetTimezone {
+        #[structopt(parse(try_from_str = parse_timezone))]
+        utc: bool,
+    },
+    /// Sets the strftime-style format of history timestamps, like "%d/%m %H:%M".
+    ///
+    /// Goes back to the default format when <format> is left out.
+    SetTimeFormat { format: Option<String> },
+    /// Adds to the balance pulls are paid from.
+    AddBalance { amount: u64 },
+    /// Sets how much balance each pull costs, 0 for free pulls.
+    SetCost { cost: u64 },
+    /// Sets the dry streak after which the rare chance ramps up.
+    ///
+    /// The ramp reaches a certain rare at the pity threshold.
+    /// Turns soft pity off when <start> is left out.
+    SetSoftPity { start: Option<usize> },
+    /// Sets whether pulled items go back into the pool: replacement or no-replacement.
+    ///
+    /// Without replacement an item can't be pulled again until every item has been.
+    /// Resets to replacement when <mode> is left out.
+    SetPullMode { mode: Option<PullMode> },
+    /// Sets whether a rare is forced when none is left in the history: true or false.
+    ///
+    /// This makes the first pull of a new list rare. Resets to true when left out.
+    SetFirstRare { guarantee: Option<bool> },
+    /// Sets a message printed when a rare is pulled, like "{name} dropped at {chance}!".
+    ///
+    /// {name} and {chance} are replaced by the pulled item's. Removes the message when
+    /// <message> is left out.
+    SetRareMessage { message: Option<String> },
+    /// Sets the minimum seconds between pulls.
+    ///
+    /// Removes the cooldown when <secs> is 0 or left out. It can be at most a year.
+    SetCooldown { secs: Option<u64> },
+    /// Sets how many pulls without a rare guarantee the next one is rare.
+    SetPity { threshold: usize },
+    /// Adds a rarity tier.
+    ///
+    /// Once a pull leaves the common tier, tiers are rolled from the rarest
+    /// down and each is kept 1 in <rarity> times.
+    /// New tiers are the rarest unless placed with --after.
+    AddTier {
+        name: PullType,
+        rarity: usize,
+        #[structopt(long = "after")]
+        after: Option<PullType>,
+    },
+    /// Sets the color a tier is shown in.
+    ///
+    /// Takes a color name like magenta or bright-magenta, or an ANSI code like 35.
+    /// Resets the tier to its default color when <color> is left out.
+    SetColor {
+        tier: PullType,
+        color: Option<String>,
+    },
+    /// Multiplies an item's chance within its tier, like a limited banner.
+    ///
+    /// Only one item is rate-up at a time.
+    RateUp {
+        #[structopt(required_unless = "clear")]
+        name: Option<String>,
+        /// How many times more likely the item is, 2 unless set before.
+        #[structopt(long = "factor")]
+        factor: Option<f64>,
+        /// Removes the rate-up.
+        #[structopt(long = "clear", conflicts_with = "name")]
+        clear: bool,
+    },
+    /// Shows the configured tiers and the chance of landing in each.
+    Tiers,
+    /// Sets the weight of landing in a tier.
+    ///
+    /// Once any weight is set, tiers are picked by their share of the weights
+    /// instead of by rarity, and tiers without a weight are never picked.
+    /// Removes the tier's weight when <weight> is left out.
+    SetTierWeight { tier: PullType, weight: Option<f64> },
+}
+
+#[derive(Debug, StructOpt)]
+enum BannerCommand {
+    /// Shows the banners.
+    #[structopt(alias = "l")]
+    List,
+    /// Creates an empty banner.
+    New { name: String },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Csv,
+}
+impl FromStr for Format {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            _ => Err("Invalid format"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ReportFormat {
+    Markdown,
+}
+impl FromStr for ReportFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err("Invalid report format"),
+        }
+    }
 }
 
 #[derive(Debug, StructOpt)]
@@ -97,34 +1311,816 @@ struct Cli {
     command: Command,
     /// File to use for the commands.
     /// Creates a file if file doesnt exist.
-    /// Defaults to ~/.config/maigach/maigacha.json
-    /// or %appdata%\maigacha\maigacha.json
-    #[structopt(short = "f", long = "file")]
+    /// Defaults to $MAIGACHA_FILE, then $XDG_CONFIG_HOME/maigacha/maigacha.json
+    /// (~/.config/maigacha/maigacha.json) or %appdata%\maigacha\maigacha.json
+    /// Reads the file from stdin when -, printing it to stdout after a command
+    /// that changes it.
+    #[structopt(short = "f", long = "file", parse(from_os_str))]
     file: Option<PathBuf>,
+    /// Save to this file instead of the one read, - prints it to stdout.
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Start a new file when the existing one can't be loaded,
+    /// overwriting it.
+    #[structopt(long = "force-new")]
+    force_new: bool,
+    /// Don't color the output.
+    /// Color is also off when NO_COLOR is set or output isn't a terminal.
+    #[structopt(long = "no-color")]
+    no_color: bool,
+    /// Print pulls, the list, history and stats as JSON instead of text.
+    #[structopt(long = "json", global = true)]
+    json: bool,
+    /// Save the file as indented JSON or TOML.
+    #[structopt(long = "pretty")]
+    pretty: bool,
+    /// Read and save the file as json or toml.
+    ///
+    /// Defaults to toml for a file ending in .toml and json otherwise.
+    #[structopt(long = "file-format")]
+    file_format: Option<FileFormat>,
+    /// Don't copy the file to <file>.bak before saving.
+    #[structopt(long = "no-backup")]
+    no_backup: bool,
+    /// Banner to use for the commands, can also be given after the command.
+    #[structopt(short = "b", long = "banner", default_value = DEFAULT_BANNER, global = true)]
+    banner: String,
+    /// Seed for the random number generator.
+    /// The same seed gives the same pulls as long as
+    /// the list and history are unchanged.
+    #[structopt(long = "seed")]
+    seed: Option<u64>,
 }
 
-fn get_maigacha_list(path: &Path) -> PullList {
-    if path.exists() {
-        return PullList::load_from_json_file(path.to_str().unwrap()).unwrap_or(PullList::new());
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+/// Turns color off for --no-color, a non-empty NO_COLOR,
+/// or when stdout isn't a terminal.
+fn init_color(no_color: bool) {
+    let enabled = !no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && io::stdout().is_terminal();
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+/// The tier's configured color, falling back to green for common and yellow otherwise.
+fn tier_color(pull_list: &PullList, pull_type: &PullType) -> String {
+    pull_list
+        .tier_color(pull_type)
+        .and_then(|color| color::escape(color).ok())
+        .unwrap_or_else(|| {
+            if pull_type.is_common() {
+                GREEN.to_owned()
+            } else {
+                YELLOW.to_owned()
+            }
+        })
+}
+
+/// The tier name wrapped in its color, when color is on.
+fn colored(pull_list: &PullList, pull_type: &PullType) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("{}{pull_type}{RESET}", tier_color(pull_list, pull_type))
+    } else {
+        pull_type.to_string()
     }
-    PullList::new()
 }
 
-fn get_default_file() -> Result<PathBuf> {
-    let file_name = MAIGACHA_FILE;
-    let mut path = if let Some(mut path) = dirs::config_dir() {
-        path.push("maigacha");
-        path
+/// Prints pulled items, grouped by item unless `verbose`, with a per-tier
+/// summary after a multi-pull.
+///
+/// As JSON a single pull is an object, or `null` when nothing was pulled.
+fn show_pulls(
+    pull_list: &PullList,
+    pulls: &[Pull],
+    multi: bool,
+    verbose: bool,
+    json: bool,
+) -> Result<Status> {
+    let status = if pulls.is_empty() {
+        Status::NothingToPull
     } else {
-        dirs::home_dir()
-            .ok_or("Could not determine home directory")?
-            .join(".maigacha")
+        Status::Success
+    };
+    if json {
+        if multi {
+            print_json(&pulls)?;
+        } else {
+            print_json(&pulls.first())?;
+        }
+        return Ok(status);
+    }
+    if pulls.is_empty() {
+        println!("Nothing to pull.");
+        return Ok(status);
+    }
+    if multi && !verbose {
+        print_grouped_pulls(pull_list, pulls);
+    } else {
+        pulls.iter().for_each(|pull| print_pull(pull_list, pull));
+    }
+    if multi {
+        print_pull_summary(pull_list, pulls);
+    }
+    let mut rares: Vec<&str> = Vec::new();
+    for pull in pulls {
+        if let Some(message) = pull_list.rare_announcement(pull) {
+            if !rares.contains(&pull.name.as_str()) {
+                rares.push(&pull.name);
+                println!("{message}");
+            }
+        }
+    }
+    let mut favorites: Vec<&str> = Vec::new();
+    for pull in pulls.iter().filter(|pull| pull.favorite) {
+        if !favorites.contains(&pull.name.as_str()) {
+            favorites.push(&pull.name);
+            println!("★ You pulled your favorite \"{}\"! ★", pull.name);
+        }
+    }
+    if pull_list.pool_resets > 0 {
+        println!("Every item was pulled, the pool was refilled.");
+    }
+    Ok(status)
+}
+
+/// Spins a "Rolling..." line on stderr for a moment, then clears it.
+///
+/// Skipped when colors are off, which covers --no-color, --json and piped stdout,
+/// or when stderr isn't a terminal.
+fn animate_roll() -> Result<()> {
+    if !COLOR.load(Ordering::Relaxed) || !io::stderr().is_terminal() {
+        return Ok(());
+    }
+    let mut stderr = io::stderr();
+    for frame in ['|', '/', '-', '\\'].iter().cycle().take(16) {
+        write!(stderr, "\r{frame} Rolling...")?;
+        stderr.flush()?;
+        thread::sleep(Duration::from_millis(75));
+    }
+    write!(stderr, "\r{}\r", " ".repeat(12))?;
+    stderr.flush()?;
+    Ok(())
+}
+
+/// Prints the shortfall to stderr if the balance can't pay for `count` pulls.
+fn can_afford(pull_list: &PullList, count: usize) -> bool {
+    if pull_list.can_afford(count as u64) {
+        return true;
+    }
+    // On stderr, so it can't end up in --json output.
+    eprintln!(
+        "Not enough balance: {count} pulls cost {}, the balance is {}.",
+        pull_list.cost.saturating_mul(count as u64),
+        pull_list.balance
+    );
+    false
+}
+
+/// Prints when the next pull is available to stderr if the cooldown hasn't passed.
+fn on_cooldown(pull_list: &PullList) -> bool {
+    let Some(remaining) = pull_list.cooldown_remaining() else {
+        return false;
+    };
+    let secs = remaining.num_seconds() + 1;
+    eprintln!("Next pull available in {}m {}s.", secs / 60, secs % 60);
+    true
+}
+
+/// Enables or disables `name`, printing what changed.
+fn set_enabled(pull_list: &mut PullList, name: &str, enabled: bool) -> Status {
+    let state = if enabled { "enabled" } else { "disabled" };
+    match pull_list.set_enabled(name, enabled) {
+        Ok(true) => println!(r#""{name}", has been {state}."#),
+        Ok(false) => println!(r#""{name}", is already {state}."#),
+        Err(err) => {
+            println!(r#""{name}", {err}."#);
+            return Status::NotFound;
+        }
+    }
+    Status::Success
+}
+
+/// The exact name of the item `name` refers to, matching it ignoring case when
+/// `ignore_case` is set.
+fn resolve_name(
+    pull_list: &PullList,
+    name: &str,
+    ignore_case: bool,
+) -> std::result::Result<String, &'static str> {
+    if !ignore_case {
+        return Ok(name.to_owned());
+    }
+    let index = pull_list.find_index(name, true)?;
+    Ok(pull_list.list()[index].name.clone())
+}
+
+/// Status for a failed edit of `name`, depending on whether it exists.
+fn not_found_or_invalid(pull_list: &PullList, name: &str) -> Status {
+    if pull_list.contains(name) {
+        Status::Invalid
+    } else {
+        Status::NotFound
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
+fn print_pull(pull_list: &PullList, pull: &Pull) {
+    println!(
+        "Pulled a {}\n{:#?} : {:#?}",
+        colored(pull_list, &pull.pull_type),
+        pull.name,
+        pull.chance
+    );
+}
+
+/// Prints each item pulled once with how many times it was, by tier then count.
+fn print_grouped_pulls(pull_list: &PullList, pulls: &[Pull]) {
+    let mut groups: Vec<(&Pull, usize)> = Vec::new();
+    for pull in pulls {
+        match groups.iter_mut().find(|(group, _)| group.name == pull.name) {
+            Some((_, count)) => *count += 1,
+            None => groups.push((pull, 1)),
+        }
+    }
+    let tier_index = |pull: &Pull| {
+        pull_list
+            .tiers
+            .iter()
+            .position(|tier| tier.name == pull.pull_type)
+            .unwrap_or(usize::MAX)
+    };
+    groups.sort_by(|(a, a_count), (b, b_count)| {
+        tier_index(a).cmp(&tier_index(b)).then(b_count.cmp(a_count))
+    });
+    for (pull, count) in groups {
+        println!(
+            "{:?} x{count} ({})",
+            pull.name,
+            colored(pull_list, &pull.pull_type)
+        );
+    }
+}
+
+fn print_pull_summary(pull_list: &PullList, pulls: &[Pull]) {
+    println!("-Summary-");
+    for tier in &pull_list.tiers {
+        let count = pulls
+            .iter()
+            .filter(|pull| pull.pull_type == tier.name)
+            .count();
+        println!("{} : {count}", colored(pull_list, &tier.name));
+    }
+}
+
+fn print_probabilities(pull_list: &PullList) {
+    let probabilities = pull_list.probabilities();
+    let max_length = probabilities
+        .iter()
+        .map(|(pull, _)| pull.label().chars().count())
+        .max()
+        .unwrap();
+    for (pull_type, _) in pull_list.tier_groups() {
+        for (pull, odds) in probabilities
+            .iter()
+            .filter(|(pull, _)| pull.pull_type == pull_type)
+        {
+            println!(
+                "{:<max_length$} : {:.4}% ({})",
+                pull.label(),
+                odds * 100_f64,
+                colored(pull_list, &pull.pull_type)
+            );
+        }
+    }
+    let total: f64 = probabilities.iter().map(|(_, odds)| odds).sum();
+    println!("Total: {:.4}%", total * 100_f64);
+    if (total - 1_f64).abs() > 1e-9 {
+        println!(
+            "The chances add up to {:.4}% instead of 100%, so some pulls would give nothing.",
+            total * 100_f64
+        );
+    }
+}
+
+fn print_simulation(pull_list: &PullList, n: usize, histogram: bool, rng: &mut dyn RngCore) {
+    let counts = pull_list.simulate(n, rng);
+    let total: usize = counts.values().sum();
+    if total == 0 {
+        println!("Nothing to pull.");
+        return;
+    }
+    if histogram {
+        print_histogram(pull_list, &counts);
+        return;
+    }
+    let rate = |count: usize| count as f64 / total as f64 * 100_f64;
+    for (pull_type, pulls) in pull_list.tier_groups() {
+        let max_length = pulls.iter().map(|pull| pull.name.len()).max().unwrap() + 2;
+        let mut tier_count = 0;
+        println!("-{} Pulls-", colored(pull_list, &pull_type));
+        for pull in pulls {
+            let count = counts.get(&pull.name).copied().unwrap_or(0);
+            tier_count += count;
+            println!(
+                "{:<max_length$} : {count} ({:.2}%)",
+                format!("\"{}\"", pull.name),
+                rate(count)
+            );
+        }
+        println!("{pull_type} hit rate: {:.2}%", rate(tier_count));
+    }
+}
+
+/// Prints a bar per item, the most pulled item's bar filling the terminal width.
+fn print_histogram(pull_list: &PullList, counts: &HashMap<String, usize>) {
+    let max_length = pull_list
+        .list()
+        .iter()
+        .map(|pull| pull.name.chars().count())
+        .max()
+        .unwrap()
+        + 2;
+    let max_count = counts.values().copied().max().unwrap_or(0).max(1);
+    let count_length = max_count.to_string().len();
+    let width = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_COLUMNS)
+        .saturating_sub(max_length + count_length + 4)
+        .max(1);
+    for (pull_type, pulls) in pull_list.tier_groups() {
+        println!("-{} Pulls-", colored(pull_list, &pull_type));
+        for pull in pulls {
+            let count = counts.get(&pull.name).copied().unwrap_or(0);
+            let bar = "#".repeat(count * width / max_count);
+            println!(
+                "{:<max_length$} | {count:>count_length$} {bar}",
+                format!("\"{}\"", pull.name)
+            );
+        }
+    }
+}
+
+fn print_stats(pull_list: &PullList) {
+    let stats = pull_list.pull_history.stats();
+    println!("Lifetime pulls: {}", pull_list.total_pulls);
+    if stats.total == 0 {
+        println!("History is empty.");
+        return;
+    }
+    println!("Pulls in history: {}", stats.total);
+    for tier in &pull_list.tiers {
+        let count = stats.counts.get(&tier.name).copied().unwrap_or(0);
+        println!(
+            "{} : {count} ({:.2}%)",
+            colored(pull_list, &tier.name),
+            count as f64 / stats.total as f64 * 100_f64
+        );
+    }
+    if let Some((name, count)) = stats.most_pulled {
+        println!("Most pulled: {name:#?} x{count}");
+    }
+    println!(
+        "Current streak: {} {}",
+        stats.current_streak,
+        if stats.current_streak_rare {
+            "rare"
+        } else {
+            "common"
+        }
+    );
+    println!("Longest common streak: {}", stats.longest_common_streak);
+    println!("Longest rare streak: {}", stats.longest_rare_streak);
+}
+
+/// Runs commands read from stdin against `banners` until exit, quit or the end
+/// of input. Returns whether any of them changed something that needs saving.
+fn repl(banners: &mut Banners, banner: &str, json: bool, rng: &mut dyn RngCore) -> Result<bool> {
+    let interactive = io::stdin().is_terminal();
+    let mut changed = false;
+    let mut line = String::new();
+    loop {
+        if interactive {
+            print!("> ");
+            io::stdout().flush()?;
+        }
+        line.clear();
+        if io::stdin().read_line(&mut line)? == 0 {
+            break;
+        }
+        let words = match split_words(&line) {
+            Ok(words) => words,
+            Err(err) => {
+                println!("{err}.");
+                continue;
+            }
+        };
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some(_) => {}
+        }
+        let args = std::iter::once("maigacha".to_owned()).chain(words);
+        let command = match Command::from_iter_safe(args) {
+            Ok(command) => command,
+            Err(err) => {
+                println!("{}", err.message);
+                continue;
+            }
+        };
+        if matches!(
+            command,
+            Command::Repl | Command::Reset { .. } | Command::Watch { .. }
+        ) {
+            println!("That command can't be run from the REPL.");
+            continue;
+        }
+        match run_command(banners, banner, command, json, rng) {
+            Ok((_, command_changed)) => changed |= command_changed,
+            Err(err) => eprintln!("Error: {err}"),
+        }
+    }
+    Ok(changed)
+}
+
+/// Set by Ctrl-C to stop `watch` between pulls.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Pulls every `interval`, waiting out any cooldown first, and saves after each
+/// pull until Ctrl-C or a pull fails.
+fn watch(
+    banners: &mut Banners,
+    banner: &str,
+    interval: Duration,
+    json: bool,
+    rng: &mut dyn RngCore,
+    save: impl Fn(&Banners) -> Result<()>,
+) -> Result<Status> {
+    ctrlc::set_handler(|| STOP.store(true, Ordering::Relaxed))?;
+    if !json {
+        println!(
+            "Pulling every {}s, press Ctrl-C to stop.",
+            interval.as_secs()
+        );
+    }
+    let mut status = Status::Success;
+    while !STOP.load(Ordering::Relaxed) {
+        let Some(pull_list) = banners.get_mut(banner) else {
+            eprintln!(
+                r#"Banner "{}" doesn't exist, create it with banner new."#,
+                banner
+            );
+            return Ok(Status::NotFound);
+        };
+        if let Some(remaining) = pull_list.cooldown_remaining() {
+            sleep_until_stopped(remaining.to_std().unwrap_or_default());
+            continue;
+        }
+        let pull = Command::Pull {
+            count: None,
+            confirm: false,
+            rare_only: false,
+            dry_run: false,
+            ignore_cooldown: false,
+            verbose: false,
+            animate: false,
+        };
+        let (pull_status, changed) = run_command(banners, banner, pull, json, rng)?;
+        if changed {
+            save(banners)?;
+        }
+        if pull_status != Status::Success {
+            status = pull_status;
+            break;
+        }
+        sleep_until_stopped(interval);
+    }
+    if !json {
+        println!("Stopped watching.");
+    }
+    Ok(status)
+}
+
+/// Sleeps for `duration`, waking early once Ctrl-C is pressed.
+fn sleep_until_stopped(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while !STOP.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(100)));
+    }
+}
+
+/// Splits a REPL line into words like a shell, keeping quoted text together.
+fn split_words(line: &str) -> std::result::Result<Vec<String>, &'static str> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.get_or_insert_with(String::new).push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => words.extend(word.take()),
+            None => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("Unclosed quote");
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn parse_timezone(s: &str) -> std::result::Result<bool, &'static str> {
+    match s.to_lowercase().as_str() {
+        "utc" => Ok(true),
+        "local" => Ok(false),
+        _ => Err("Invalid timezone, expected utc or local"),
+    }
+}
+
+fn parse_since(s: &str) -> std::result::Result<DateTime<Local>, String> {
+    parse_date(s, NaiveTime::MIN)
+}
+
+fn parse_until(s: &str) -> std::result::Result<DateTime<Local>, String> {
+    parse_date(
+        s,
+        NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap(),
+    )
+}
+
+/// Parses a local date with an optional time, using `default_time` for bare dates.
+fn parse_date(s: &str, default_time: NaiveTime) -> std::result::Result<DateTime<Local>, String> {
+    let s = s.trim();
+    let date_time = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_time(default_time))
+    })
+    .ok_or_else(|| format!(r#"Invalid date "{s}", expected YYYY-MM-DD[ HH:MM[:SS]]"#))?;
+    Local
+        .from_local_datetime(&date_time)
+        .earliest()
+        .ok_or_else(|| format!(r#"Invalid local time "{s}""#))
+}
+
+fn run_banner_command(banners: &mut Banners, command: BannerCommand) -> (Status, bool) {
+    match command {
+        BannerCommand::List => {
+            for (name, pull_list) in &banners.banners {
+                println!("{name} ({} items)", pull_list.list().len());
+            }
+            (Status::Success, false)
+        }
+        BannerCommand::New { name } => {
+            if banners.create(&name) {
+                println!(r#"Banner "{name}" created."#);
+                (Status::Success, true)
+            } else {
+                println!(r#"Banner "{name}" already exists."#);
+                (Status::Invalid, false)
+            }
+        }
+    }
+}
+
+fn reset(
+    path: &Path,
+    yes: bool,
+    format: Option<FileFormat>,
+    backup: bool,
+    pretty: bool,
+) -> Result<Status> {
+    if !yes {
+        println!("Resetting deletes every banner, item and pull, pass --yes to confirm.");
+        return Ok(Status::Invalid);
+    }
+    if backup && path != Path::new(STDIN_FILE) && path.exists() {
+        let timestamp = Local::now().format("%Y%m%d%H%M%S");
+        let backup_path = format!("{}.{timestamp}.bak", path.display());
+        std::fs::copy(path, &backup_path)?;
+        println!("Backed up {} to {backup_path}.", path.display());
+    }
+    save(&Banners::new(), path, format, false, pretty)?;
+    println!(
+        "Reset {} to an empty {DEFAULT_BANNER} banner.",
+        path.display()
+    );
+    Ok(Status::Success)
+}
+
+/// Saves to `path`, or prints the file when it's `-`.
+///
+/// Without a `format`, it's picked from the extension of `path`.
+fn save(
+    banners: &Banners,
+    path: &Path,
+    format: Option<FileFormat>,
+    backup: bool,
+    pretty: bool,
+) -> Result<()> {
+    let format = format.unwrap_or_else(|| FileFormat::from_path(path));
+    if path == Path::new(STDIN_FILE) {
+        println!("{}", banners.to_text(format, pretty)?);
+        return Ok(());
+    }
+    banners.save_to_file(path, format, backup, pretty)
+}
+
+/// Loads banners from `path`, or from stdin when it's `-`.
+fn get_banners(path: &Path, format: Option<FileFormat>, force_new: bool) -> Result<Banners> {
+    let format = format.unwrap_or_else(|| FileFormat::from_path(path));
+    let loaded = if path == Path::new(STDIN_FILE) {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Banners::load_from_str(&text, format)
+    } else if !path.exists() {
+        return Ok(Banners::new());
+    } else {
+        Banners::load_from_file(path, format)
     };
+    match loaded {
+        Ok(banners) => Ok(banners),
+        Err(_) if force_new => Ok(Banners::new()),
+        Err(err) => Err(format!(
+            "Could not load {}: {err}. Fix the file or pass --force-new to start over.",
+            path.display()
+        )
+        .into()),
+    }
+}
 
-    if !path.exists() {
-        std::fs::create_dir_all(&path)?;
+/// The file to use: `--file`, then the `MAIGACHA_FILE` environment variable,
+/// then the default file.
+fn resolve_file(file: Option<PathBuf>) -> Result<PathBuf> {
+    match chosen_file(file, std::env::var_os(FILE_ENV)) {
+        Some(path) => Ok(path),
+        None => get_default_file(),
     }
+}
+
+/// `file` from `--file`, else `env_file` from `MAIGACHA_FILE` unless it's empty.
+fn chosen_file(file: Option<PathBuf>, env_file: Option<OsString>) -> Option<PathBuf> {
+    file.or_else(|| env_file.filter(|path| !path.is_empty()).map(PathBuf::from))
+}
 
-    path.push(file_name);
+fn get_default_file() -> Result<PathBuf> {
+    default_file_in(config_dir(), dirs::home_dir())
+}
+
+/// `maigacha/maigacha.json` in `config_dir`, or `.maigacha/maigacha.json` in
+/// `home_dir` without one.
+///
+/// A file left in the misspelled `maigach` directory by older versions is moved over.
+fn default_file_in(config_dir: Option<PathBuf>, home_dir: Option<PathBuf>) -> Result<PathBuf> {
+    let dir = match &config_dir {
+        Some(config_dir) => config_dir.join("maigacha"),
+        None => home_dir
+            .ok_or("Could not determine home directory")?
+            .join(".maigacha"),
+    };
+    std::fs::create_dir_all(&dir)?;
+
+    let path = dir.join(MAIGACHA_FILE);
+    if let Some(config_dir) = &config_dir {
+        let legacy = config_dir.join(LEGACY_DIR).join(MAIGACHA_FILE);
+        if !path.exists() && legacy.exists() {
+            std::fs::rename(&legacy, &path)?;
+            eprintln!("Moved {} to {}.", legacy.display(), path.display());
+        }
+    }
     Ok(path)
 }
+
+fn config_dir() -> Option<PathBuf> {
+    config_dir_from(std::env::var_os("XDG_CONFIG_HOME"), dirs::config_dir())
+}
+
+/// `xdg` from `$XDG_CONFIG_HOME` when it's an absolute path on Linux, else
+/// `platform_dir`, the platform's config directory.
+fn config_dir_from(xdg: Option<OsString>, platform_dir: Option<PathBuf>) -> Option<PathBuf> {
+    if cfg!(target_os = "linux") {
+        if let Some(dir) = xdg.map(PathBuf::from).filter(|dir| dir.is_absolute()) {
+            return Some(dir);
+        }
+    }
+    platform_dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory under the system temp directory, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("maigacha-{}-{name}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn file_flag_beats_environment() {
+        let flag = Some(PathBuf::from("flag.json"));
+        let env = Some(OsString::from("env.json"));
+        assert_eq!(
+            chosen_file(flag, env.clone()),
+            Some(PathBuf::from("flag.json"))
+        );
+        assert_eq!(chosen_file(None, env), Some(PathBuf::from("env.json")));
+        assert_eq!(chosen_file(None, Some(OsString::new())), None);
+        assert_eq!(chosen_file(None, None), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn config_dir_uses_absolute_xdg_config_home() {
+        let platform = Some(PathBuf::from("/platform"));
+        let set = Some(OsString::from("/xdg"));
+        assert_eq!(
+            config_dir_from(set, platform.clone()),
+            Some(PathBuf::from("/xdg"))
+        );
+        assert_eq!(config_dir_from(None, platform.clone()), platform);
+        let relative = Some(OsString::from("xdg"));
+        assert_eq!(config_dir_from(relative, platform.clone()), platform);
+        assert_eq!(
+            config_dir_from(Some(OsString::new()), platform.clone()),
+            platform
+        );
+    }
+
+    #[test]
+    fn default_file_falls_back_to_home() {
+        let temp = TempDir::new("home");
+        let path = default_file_in(None, Some(temp.0.clone())).unwrap();
+        assert_eq!(path, temp.0.join(".maigacha").join(MAIGACHA_FILE));
+        assert!(temp.0.join(".maigacha").is_dir());
+        assert!(default_file_in(None, None).is_err());
+    }
+
+    #[test]
+    fn default_file_moves_legacy_file() {
+        let temp = TempDir::new("legacy");
+        let legacy_dir = temp.0.join(LEGACY_DIR);
+        std::fs::create_dir_all(&legacy_dir).unwrap();
+        std::fs::write(legacy_dir.join(MAIGACHA_FILE), "legacy").unwrap();
+
+        let path = default_file_in(Some(temp.0.clone()), None).unwrap();
+        assert_eq!(path, temp.0.join("maigacha").join(MAIGACHA_FILE));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "legacy");
+        assert!(!legacy_dir.join(MAIGACHA_FILE).exists());
+    }
+
+    #[test]
+    fn default_file_keeps_existing_file_over_legacy() {
+        let temp = TempDir::new("both");
+        let legacy_dir = temp.0.join(LEGACY_DIR);
+        std::fs::create_dir_all(&legacy_dir).unwrap();
+        std::fs::write(legacy_dir.join(MAIGACHA_FILE), "legacy").unwrap();
+        std::fs::create_dir_all(temp.0.join("maigacha")).unwrap();
+        std::fs::write(temp.0.join("maigacha").join(MAIGACHA_FILE), "current").unwrap();
+
+        let path = default_file_in(Some(temp.0.clone()), None).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "current");
+        assert!(legacy_dir.join(MAIGACHA_FILE).exists());
+    }
+}